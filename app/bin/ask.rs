@@ -17,6 +17,7 @@ use askbend::APIHandler;
 use askbend::Config;
 use askbend::GithubComment;
 use askbend::QAEmbedding;
+use askbend::QAEval;
 use env_logger::Builder;
 use env_logger::Env;
 use log::info;
@@ -30,13 +31,23 @@ async fn main() -> Result<()> {
     let conf = Config::load()?;
     info!("config: {:?}", conf);
 
-    if conf.qa.rebuild {
+    if conf.qa.rebuild && conf.qa.dry_run {
+        let conf = conf.with_collection(&conf.qa.collection)?;
+        let qa_embedding = QAEmbedding::create(&conf);
+        let stats = qa_embedding.dry_run().await?;
+        println!("{}", stats.report());
+    } else if conf.qa.rebuild {
+        let conf = conf.with_collection(&conf.qa.collection)?;
         let now = Instant::now();
         let qa_embedding = QAEmbedding::create(&conf);
         qa_embedding.rebuild().await?;
         info!("QA rebuild done, cost:{}", now.elapsed().as_secs());
+    } else if !conf.qa.eval.is_empty() {
+        let conf = conf.with_collection(&conf.qa.collection)?;
+        let report = QAEval::create(&conf)?.eval().await?;
+        println!("{}", report.summary());
     } else {
-        let github_comments = GithubComment::create(&conf);
+        let github_comments = GithubComment::create(&conf)?;
         github_comments.start();
 
         start_api_server(&conf).await?;
@@ -18,9 +18,13 @@ use actix_web::web;
 use actix_web::App;
 use actix_web::HttpServer;
 use anyhow::Result;
+use log::info;
 
+use crate::api::qa_collection_query_handler;
 use crate::api::qa_query_handler;
+use crate::api::qa_search_handler;
 use crate::Config;
+use crate::QADatabase;
 
 pub struct APIHandler {
     pub conf: Config,
@@ -32,6 +36,8 @@ impl APIHandler {
     }
 
     pub async fn start(self) -> Result<()> {
+        self.init_answer_tables().await?;
+
         let conf = self.conf.clone();
         let host = conf.server.host.clone();
         let port = conf.server.port;
@@ -57,6 +63,11 @@ impl APIHandler {
                 .app_data(web::Data::new(conf.clone()))
                 .route("/query", web::post().to(qa_query_handler))
                 .route("/qa/query", web::post().to(qa_query_handler))
+                .route("/qa/search", web::post().to(qa_search_handler))
+                .route(
+                    "/qa/{collection}/query",
+                    web::post().to(qa_collection_query_handler),
+                )
         })
         .bind(format!("{}:{}", host, port))?
         .run()
@@ -64,4 +75,24 @@ impl APIHandler {
 
         Ok(())
     }
+
+    /// Create or migrate the answer tables of the default and the other collections.
+    async fn init_answer_tables(&self) -> Result<()> {
+        let mut tables = vec![];
+        let names = std::iter::once("").chain(self.conf.qa.collection_names());
+        for name in names {
+            let conf = self.conf.with_collection(name)?;
+            let table = format!("{}.{}", conf.qa.database, conf.qa.answer_table);
+            if conf.qa.answer_table.is_empty() || tables.contains(&table) {
+                continue;
+            }
+            info!("Init answer table {}", table);
+            QADatabase::connect(&conf)
+                .await?
+                .init_answer_table()
+                .await?;
+            tables.push(table);
+        }
+        Ok(())
+    }
 }
@@ -15,5 +15,8 @@
 mod http;
 mod qa;
 
-pub use http::APIHandler;
+pub use qa::qa_collection_query_handler;
 pub use qa::qa_query_handler;
+pub use qa::qa_search_handler;
+
+pub use self::http::APIHandler;
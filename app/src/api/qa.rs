@@ -12,42 +12,205 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use actix_web::http::header;
 use actix_web::http::StatusCode;
 use actix_web::web;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Responder;
+use anyhow::Result;
 use log::error;
 
 use crate::Config;
+use crate::QAAnswer;
+use crate::QAExplain;
+use crate::QASearch;
+use crate::QASearchFilter;
 use crate::QALLM;
 
 #[derive(serde::Deserialize)]
 pub struct QAQuery {
     query: String,
+    /// Collection to query, the default one if empty.
+    #[serde(default)]
+    collection: String,
+    /// Collections to search together, overrides `collection` if not empty.
+    #[serde(default)]
+    collections: Vec<String>,
+    /// Metadata filter of the retrieved chunks.
+    #[serde(default)]
+    filter: QASearchFilter,
+    /// Return how the answer was made, only for the admin keys.
+    #[serde(default)]
+    debug: bool,
+}
+
+/// Max hits of a search page.
+const MAX_PAGE_SIZE: usize = 50;
+/// Max search page, the pages before it are searched too.
+const MAX_PAGE: usize = 100;
+
+#[derive(serde::Deserialize)]
+pub struct QASearchQuery {
+    query: String,
+    /// Collection to search, the default one if empty.
+    #[serde(default)]
+    collection: String,
+    /// Collections to search together, overrides `collection` if not empty.
+    #[serde(default)]
+    collections: Vec<String>,
+    #[serde(default)]
+    filter: QASearchFilter,
+    /// Page of the results, from 1.
+    #[serde(default = "default_page")]
+    page: usize,
+    #[serde(default = "default_page_size")]
+    page_size: usize,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_page_size() -> usize {
+    10
 }
 
 #[derive(serde::Serialize)]
 struct Response {
     result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<QAExplain>,
 }
 
 /// curl -X POST -H "Content-Type: application/json" -d '{"query": "whats the fast way to load data to databend"}' http://localhost:8081/query
+/// curl -X POST -H "Authorization: Bearer <admin key>" -H "Content-Type: application/json" -d '{"query": "how to load csv", "debug": true}' http://localhost:8081/qa/query
 pub async fn qa_query_handler(
+    req: HttpRequest,
+    query: web::Json<QAQuery>,
+    conf: web::Data<Config>,
+) -> impl Responder {
+    if query.debug && !is_admin(&req, &conf) {
+        return forbidden();
+    }
+    if !query.collections.is_empty() {
+        return query_collections(&conf, &query.collections, &query, query.debug).await;
+    }
+    query_collection(&conf, &query.collection, &query, query.debug).await
+}
+
+/// curl -X POST -H "Content-Type: application/json" -d '{"query": "how to restart a warehouse"}' http://localhost:8081/qa/runbooks/query
+pub async fn qa_collection_query_handler(
+    req: HttpRequest,
+    collection: web::Path<String>,
     query: web::Json<QAQuery>,
     conf: web::Data<Config>,
 ) -> impl Responder {
-    let llm = QALLM::create(&conf);
-    let result = llm.query(&query.query).await;
+    if query.debug && !is_admin(&req, &conf) {
+        return forbidden();
+    }
+    query_collection(&conf, &collection, &query, query.debug).await
+}
+
+async fn query_collection(
+    conf: &Config,
+    collection: &str,
+    query: &QAQuery,
+    debug: bool,
+) -> HttpResponse {
+    let conf = match conf.with_collection(collection) {
+        Ok(conf) => conf,
+        Err(e) => return HttpResponse::NotFound().json(e.to_string()),
+    };
+
+    let llm = QALLM::create(&conf).with_filter(&query.filter);
+    let result = llm.explain(&query.query, None).await;
+    response(result, debug)
+}
+
+/// curl -X POST -H "Content-Type: application/json" -d '{"query": "how to restart a warehouse", "collections": ["default", "runbooks"]}' http://localhost:8081/qa/query
+async fn query_collections(
+    conf: &Config,
+    collections: &[String],
+    query: &QAQuery,
+    debug: bool,
+) -> HttpResponse {
+    if let Some(e) = collections
+        .iter()
+        .find_map(|x| conf.qa.collection_config(x).err())
+    {
+        return HttpResponse::NotFound().json(e.to_string());
+    }
+
+    let llm = QALLM::create(conf).with_filter(&query.filter);
+    let result = llm.explain(&query.query, Some(collections)).await;
+    response(result, debug)
+}
+
+/// curl -X POST -H "Content-Type: application/json" -d '{"query": "load csv", "filter": {"path": "12-load-data/"}, "page": 2}' http://localhost:8081/qa/search
+pub async fn qa_search_handler(
+    query: web::Json<QASearchQuery>,
+    conf: web::Data<Config>,
+) -> impl Responder {
+    if query.page_size == 0 || query.page_size > MAX_PAGE_SIZE {
+        return HttpResponse::BadRequest()
+            .json(format!("page_size must be between 1 and {}", MAX_PAGE_SIZE));
+    }
+    if query.page == 0 || query.page > MAX_PAGE {
+        return HttpResponse::BadRequest().json(format!("page must be between 1 and {}", MAX_PAGE));
+    }
+
+    let (conf, collections) = if query.collections.is_empty() {
+        match conf.with_collection(&query.collection) {
+            Ok(conf) => (conf, None),
+            Err(e) => return HttpResponse::NotFound().json(e.to_string()),
+        }
+    } else {
+        if let Some(e) = query
+            .collections
+            .iter()
+            .find_map(|x| conf.qa.collection_config(x).err())
+        {
+            return HttpResponse::NotFound().json(e.to_string());
+        }
+        (conf.get_ref().clone(), Some(query.collections.as_slice()))
+    };
+
+    let search = QASearch::create(&conf).with_filter(&query.filter);
+    match search
+        .search(&query.query, collections, query.page, query.page_size)
+        .await
+    {
+        Ok(page) => HttpResponse::Ok().json(page),
+        Err(e) => {
+            error!("search handler error:{:?}", e);
+            HttpResponse::build(StatusCode::INTERNAL_SERVER_ERROR).json(format!("{:?}", e))
+        }
+    }
+}
+
+fn is_admin(req: &HttpRequest, conf: &Config) -> bool {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .map(|x| conf.server.is_admin(x))
+        .unwrap_or(false)
+}
+
+fn forbidden() -> HttpResponse {
+    HttpResponse::Forbidden().json("debug is only allowed with an admin key")
+}
+
+fn response(result: Result<QAAnswer>, debug: bool) -> HttpResponse {
     match result {
-        Ok(result) => {
-            let response = if !result.is_empty() {
-                Response { result }
+        Ok(answer) => {
+            let result = if !answer.answer.is_empty() {
+                answer.answer
             } else {
-                Response {
-                    result: "Sorry, I dont know how to help with that.".to_string(),
-                }
+                QAAnswer::FALLBACK.to_string()
             };
-            HttpResponse::Ok().json(response)
+            let debug = if debug { Some(answer.explain) } else { None };
+            HttpResponse::Ok().json(Response { result, debug })
         }
         Err(e) => {
             error!("query handler error:{:?}", e);
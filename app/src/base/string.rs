@@ -19,3 +19,21 @@ pub fn escape_sql_string(input: &str) -> String {
         .replace('\n', " ")
         .replace('\r', "\\r")
 }
+
+/// The `ARRAY(VARCHAR)` literal of the values, eg: `['a','b''s']`.
+pub fn escape_sql_array(values: &[String]) -> String {
+    let values = values
+        .iter()
+        .map(|x| format!("'{}'", escape_sql_string(x)))
+        .collect::<Vec<_>>();
+    format!("[{}]", values.join(","))
+}
+
+/// Escape the `LIKE` wildcards of the input, to match it literally in a pattern.
+/// The pattern still needs `escape_sql_string` in the SQL.
+pub fn escape_sql_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
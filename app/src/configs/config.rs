@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use std::env;
+use std::fmt;
 use std::fmt::Debug;
+use std::fmt::Formatter;
 
 use anyhow::Result;
 use clap::Parser;
@@ -28,7 +30,7 @@ use crate::configs::GithubConfig;
 use crate::configs::LogConfig;
 use crate::configs::QAConfig;
 
-#[derive(Parser, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Parser, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default, deny_unknown_fields)]
 pub struct ServerConfig {
     #[clap(long = "host", default_value_t)]
@@ -37,6 +39,20 @@ pub struct ServerConfig {
     pub port: usize,
 
     pub cors: Vec<String>,
+    /// Keys of the admins, sent as `Authorization: Bearer <key>` for the debug queries.
+    #[clap(skip)]
+    pub admin_keys: Vec<String>,
+}
+
+impl Debug for ServerConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("cors", &self.cors)
+            .field("admin_keys", &"******")
+            .finish()
+    }
 }
 
 impl Default for ServerConfig {
@@ -45,6 +61,20 @@ impl Default for ServerConfig {
             host: "".to_string(),
             port: 0,
             cors: Vec::new(),
+            admin_keys: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Whether the `Authorization` header value carries an admin key.
+    pub fn is_admin(&self, authorization: &str) -> bool {
+        match authorization.strip_prefix("Bearer ") {
+            Some(key) => {
+                let key = key.trim();
+                !key.is_empty() && self.admin_keys.iter().any(|x| x == key)
+            }
+            None => false,
         }
     }
 }
@@ -107,6 +137,18 @@ impl Config {
 
         // Finally, load from args.
         builder = builder.collect(from_self(arg_conf));
-        builder.build()
+        let conf = builder.build()?;
+
+        conf.qa.validate()?;
+        conf.github.validate()?;
+        Ok(conf)
+    }
+
+    /// The config with the qa config of the named collection, the `[qa]` one if the name is empty.
+    pub fn with_collection(&self, name: &str) -> Result<Config> {
+        Ok(Config {
+            qa: self.qa.collection_config(name)?,
+            ..self.clone()
+        })
     }
 }
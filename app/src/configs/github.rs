@@ -16,16 +16,32 @@
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::str::FromStr;
 
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
 use clap::Parser;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::github::GithubCommand;
+use crate::github::ReviewSeverity;
+
 #[derive(Parser, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default, deny_unknown_fields)]
 pub struct GithubConfig {
     #[clap(long = "github_token", default_value_t)]
     pub github_token: String,
+    /// GitHub API base url, set for GitHub Enterprise, eg: https://github.example.com/api/v3
+    #[clap(long = "github_api_base_url", default_value_t)]
+    pub api_base_url: String,
+    /// GitHub App id, authenticate as the GitHub App installations instead of github_token if set
+    #[clap(long = "github_app_id", default_value_t)]
+    pub app_id: u64,
+    /// Path of the GitHub App private key in PEM format
+    #[clap(long = "github_app_private_key_path", default_value_t)]
+    pub app_private_key_path: String,
     #[clap(long = "llm_max_tokens", default_value_t = 100000)]
     pub llm_max_tokens: usize,
     #[clap(long = "databend_dsn", default_value_t)]
@@ -36,16 +52,62 @@ pub struct GithubConfig {
 
     #[clap(long = "check_in_secs", default_value_t = 20)]
     pub check_in_secs: usize,
+    /// Max retries of a failed GitHub request or step, rate limited requests wait for the reset.
+    #[clap(long = "github_max_retries", default_value_t = 3)]
+    pub max_retries: usize,
+    /// Initial delay of the exponential backoff between the retries.
+    #[clap(long = "github_retry_delay_ms", default_value_t = 1000)]
+    pub retry_delay_ms: u64,
+
+    /// Glob patterns of the vendored or generated files to skip in the diff.
+    #[clap(skip = default_generated_paths())]
+    pub generated_paths: Vec<String>,
+    /// Directory depth to group the file patches of a large PR.
+    #[clap(long = "summary_group_depth", default_value_t = 2)]
+    pub summary_group_depth: usize,
+    /// Max groups to summarize of a large PR.
+    #[clap(long = "summary_max_groups", default_value_t = 20)]
+    pub summary_max_groups: usize,
+
+    /// Path of the repo config file in the repo default branch, disabled if empty.
+    #[clap(long = "repo_config_path", default_value = ".askbend.toml")]
+    pub repo_config_path: String,
+    #[clap(skip)]
+    pub repo_configs: Vec<GithubRepoConfig>,
+
+    /// Re-summarize the PRs which have a summary comment when new commits are pushed.
+    #[clap(long = "auto_update_summary", default_value_t)]
+    pub auto_update_summary: bool,
+
+    // review
+    #[clap(long = "review_max_comments", default_value_t = 10)]
+    pub review_max_comments: usize,
+    /// Min severity of the review comments <low|medium|high>
+    #[clap(long = "review_min_severity", default_value = "medium")]
+    pub review_min_severity: String,
 }
 
 impl Debug for GithubConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("QAConfig")
             .field("github_token", &"******")
+            .field("api_base_url", &self.api_base_url)
+            .field("app_id", &self.app_id)
+            .field("app_private_key_path", &self.app_private_key_path)
             .field("databend_dsn", &"******")
             .field("llm_max_tokens", &self.llm_max_tokens)
             .field("repos", &self.repos)
             .field("check_in_secs", &self.check_in_secs)
+            .field("max_retries", &self.max_retries)
+            .field("retry_delay_ms", &self.retry_delay_ms)
+            .field("generated_paths", &self.generated_paths)
+            .field("summary_group_depth", &self.summary_group_depth)
+            .field("summary_max_groups", &self.summary_max_groups)
+            .field("repo_config_path", &self.repo_config_path)
+            .field("repo_configs", &self.repo_configs)
+            .field("auto_update_summary", &self.auto_update_summary)
+            .field("review_max_comments", &self.review_max_comments)
+            .field("review_min_severity", &self.review_min_severity)
             .finish()
     }
 }
@@ -54,10 +116,277 @@ impl Default for GithubConfig {
     fn default() -> Self {
         GithubConfig {
             github_token: "".to_string(),
+            api_base_url: "".to_string(),
+            app_id: 0,
+            app_private_key_path: "".to_string(),
             llm_max_tokens: 100000,
             databend_dsn: "".to_string(),
             repos: None,
             check_in_secs: 20,
+            max_retries: 3,
+            retry_delay_ms: 1000,
+            generated_paths: default_generated_paths(),
+            summary_group_depth: 2,
+            summary_max_groups: 20,
+            repo_config_path: ".askbend.toml".to_string(),
+            repo_configs: vec![],
+            auto_update_summary: false,
+            review_max_comments: 10,
+            review_min_severity: "medium".to_string(),
+        }
+    }
+}
+
+fn default_generated_paths() -> Vec<String> {
+    vec![
+        "vendor/**".to_string(),
+        "**/vendor/**".to_string(),
+        "**/*.lock".to_string(),
+        "**/*.min.js".to_string(),
+        "**/*.pb.go".to_string(),
+        "**/*_pb2.py".to_string(),
+        "**/package-lock.json".to_string(),
+    ]
+}
+
+impl GithubConfig {
+    /// All the repos to scan, from `repos` and `repo_configs`.
+    pub fn all_repos(&self) -> Vec<String> {
+        let mut repos = self.repos.clone().unwrap_or_default();
+        for repo_config in &self.repo_configs {
+            if !repos.iter().any(|x| Self::same_repo(x, &repo_config.repo)) {
+                repos.push(repo_config.repo.clone());
+            }
+        }
+        repos
+    }
+
+    /// The config of the repo, the default settings if not configured.
+    pub fn repo_config(&self, repo: &str) -> GithubRepoConfig {
+        self.repo_configs
+            .iter()
+            .find(|x| Self::same_repo(&x.repo, repo))
+            .cloned()
+            .unwrap_or_else(|| GithubRepoConfig {
+                repo: repo.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Check all the repos can be parsed, the error lists all the bad entries.
+    /// The review severity is checked too, before any review is paid for.
+    pub fn validate(&self) -> Result<()> {
+        ReviewSeverity::from_str(&self.review_min_severity)
+            .with_context(|| "invalid review_min_severity, expect low, medium or high")?;
+        for repo_config in &self.repo_configs {
+            repo_config.validate()?;
+        }
+
+        let errors: Vec<String> = self
+            .all_repos()
+            .iter()
+            .filter_map(|x| Self::parse_repo(x).err())
+            .map(|e| e.to_string())
+            .collect();
+        if !errors.is_empty() {
+            bail!(
+                "invalid github repos, expect owner/repo, https://github.com/owner/repo or git@github.com:owner/repo.git:\n  {}",
+                errors.join("\n  ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Parse the owner and name of the repo, the accepted forms:
+    ///
+    /// - `owner/repo`
+    /// - `github.com/owner/repo`
+    /// - `https://github.com/owner/repo`, with optional `.git` suffix, trailing slash or sub path
+    /// - `git@github.com:owner/repo.git`
+    /// - `ssh://git@github.com/owner/repo.git`
+    pub fn parse_repo(repo: &str) -> Result<(String, String)> {
+        let trimmed = repo.trim();
+        let trimmed = trimmed.split(['?', '#']).next().unwrap_or_default();
+
+        let path = if let Some((_, rest)) = trimmed.split_once("://") {
+            // Skip the host of the url.
+            rest.split_once('/')
+                .map(|(_, path)| path)
+                .unwrap_or_default()
+        } else if let Some((host, path)) = trimmed.split_once(':') {
+            // scp-like syntax, `git@github.com:owner/repo.git`.
+            if host.is_empty() || host.contains('/') {
+                bail!("{:?}: unknown format", repo);
+            }
+            path
+        } else {
+            match trimmed.split_once('/') {
+                // Owners can't contain `.`, so the first segment with `.` is the host.
+                Some((host, path)) if host.contains('.') => path,
+                _ => trimmed,
+            }
+        };
+
+        let mut segments = path.split('/').filter(|x| !x.is_empty());
+        let (owner, name) = match (segments.next(), segments.next()) {
+            (Some(owner), Some(name)) => (owner, name.strip_suffix(".git").unwrap_or(name)),
+            _ => bail!("{:?}: missing owner or repo name", repo),
+        };
+
+        let is_valid = |x: &str| {
+            !x.is_empty()
+                && x.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        };
+        if !is_valid(owner) || !is_valid(name) {
+            bail!("{:?}: invalid owner or repo name", repo);
+        }
+        Ok((owner.to_string(), name.to_string()))
+    }
+
+    /// Check if the two repo identifiers are the same repo, eg: `owner/repo` and `https://github.com/owner/repo.git`.
+    fn same_repo(a: &str, b: &str) -> bool {
+        match (Self::parse_repo(a), Self::parse_repo(b)) {
+            (Ok((a_owner, a_name)), Ok((b_owner, b_name))) => {
+                a_owner.eq_ignore_ascii_case(&b_owner) && a_name.eq_ignore_ascii_case(&b_name)
+            }
+            _ => a == b,
+        }
+    }
+}
+
+/// Config of one repo, `[[github.repo_configs]]` in the config file.
+/// The repo config file (`.askbend.toml`) in the repo is merged into it if exists.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GithubRepoConfig {
+    pub repo: String,
+    /// Enabled commands <summary|review>, all if empty.
+    pub commands: Vec<String>,
+    /// Users allowed to trigger the commands, anyone if both users and teams are empty.
+    pub allowed_users: Vec<String>,
+    /// Teams allowed to trigger the commands, format as `org/team-slug`.
+    pub allowed_teams: Vec<String>,
+    /// Language of the summary, English if empty.
+    pub language: String,
+    /// Max tokens of the diff to summarize, `github.llm_max_tokens` if 0.
+    pub llm_max_tokens: usize,
+    pub chunk_size: usize,
+    /// Glob patterns of the ignored paths, relative to the repo root, eg: `Cargo.lock`, `**/*.pb.go`.
+    pub ignored_paths: Vec<String>,
+}
+
+impl Default for GithubRepoConfig {
+    fn default() -> Self {
+        GithubRepoConfig {
+            repo: "".to_string(),
+            commands: vec![],
+            allowed_users: vec![],
+            allowed_teams: vec![],
+            language: "".to_string(),
+            llm_max_tokens: 0,
+            chunk_size: 8000,
+            ignored_paths: vec![],
+        }
+    }
+}
+
+impl GithubRepoConfig {
+    /// Check the command names, a typo would disable the command silently.
+    pub fn validate(&self) -> Result<()> {
+        let unknown: Vec<&String> = self
+            .commands
+            .iter()
+            .filter(|x| GithubCommand::from_name(x).is_none())
+            .collect();
+        if !unknown.is_empty() {
+            bail!(
+                "unknown commands {:?} of repo {}, expect summary or review",
+                unknown,
+                self.repo
+            );
+        }
+        Ok(())
+    }
+
+    /// Merge the repo config file in the repo into the config, field by field.
+    /// The file can't widen the access: its allowed users and teams only apply if the config
+    /// allows anyone, and its commands only narrow the commands of the config.
+    pub fn merge_file(&self, file: GithubRepoConfig) -> GithubRepoConfig {
+        let commands = if file.commands.is_empty() {
+            self.commands.clone()
+        } else if self.commands.is_empty() {
+            file.commands
+        } else {
+            let commands: Vec<String> = self
+                .commands
+                .iter()
+                .filter(|x| file.commands.contains(x))
+                .cloned()
+                .collect();
+            // Empty would enable all the commands.
+            if commands.is_empty() {
+                self.commands.clone()
+            } else {
+                commands
+            }
+        };
+        let (allowed_users, allowed_teams) =
+            if self.allowed_users.is_empty() && self.allowed_teams.is_empty() {
+                (file.allowed_users, file.allowed_teams)
+            } else {
+                (self.allowed_users.clone(), self.allowed_teams.clone())
+            };
+        let mut ignored_paths = self.ignored_paths.clone();
+        for path in file.ignored_paths {
+            if !ignored_paths.contains(&path) {
+                ignored_paths.push(path);
+            }
         }
+
+        GithubRepoConfig {
+            repo: self.repo.clone(),
+            commands,
+            allowed_users,
+            allowed_teams,
+            language: if file.language.is_empty() {
+                self.language.clone()
+            } else {
+                file.language
+            },
+            llm_max_tokens: if file.llm_max_tokens == 0 {
+                self.llm_max_tokens
+            } else {
+                file.llm_max_tokens
+            },
+            chunk_size: if file.chunk_size == GithubRepoConfig::default().chunk_size {
+                self.chunk_size
+            } else {
+                file.chunk_size
+            },
+            ignored_paths,
+        }
+    }
+
+    pub fn is_enabled(&self, command: &str) -> bool {
+        self.commands.is_empty() || self.commands.iter().any(|x| x == command)
+    }
+
+    pub fn max_tokens(&self, conf: &GithubConfig) -> usize {
+        if self.llm_max_tokens == 0 {
+            conf.llm_max_tokens
+        } else {
+            self.llm_max_tokens
+        }
+    }
+
+    /// Skip patterns for the diff splitter, from the ignored paths and the generated paths.
+    /// The diff paths are prefixed with `a/` or `b/`.
+    pub fn diff_skips(&self, conf: &GithubConfig) -> Vec<String> {
+        conf.generated_paths
+            .iter()
+            .chain(self.ignored_paths.iter())
+            .flat_map(|x| vec![x.clone(), format!("[ab]/{}", x)])
+            .collect()
     }
 }
@@ -19,6 +19,7 @@ mod qa;
 
 pub use config::*;
 pub use github::GithubConfig;
+pub use github::GithubRepoConfig;
 pub use qa::*;
 
 pub use self::log::LogConfig;
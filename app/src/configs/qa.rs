@@ -16,7 +16,10 @@
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::path::Path;
 
+use anyhow::bail;
+use anyhow::Result;
 use clap::Parser;
 use serde::Deserialize;
 use serde::Serialize;
@@ -28,6 +31,66 @@ pub struct QAConfig {
     #[clap(long = "path", default_value = "data/")]
     pub path: String,
 
+    // git source, the docs are loaded from the git repo instead of `path` if `git_url` is set.
+    #[clap(long = "git_url", default_value_t)]
+    pub git_url: String,
+    #[clap(long = "git_branch", default_value = "main")]
+    pub git_branch: String,
+    /// Directory of the docs in the git repo, the repo root if empty.
+    #[clap(long = "git_subdir", default_value_t)]
+    pub git_subdir: String,
+    /// Local checkout of the git repo, kept between the rebuilds to fetch only the new commits.
+    /// Keep it out of `path`, or the checkout is loaded as the docs of the local source.
+    #[clap(long = "git_checkout_dir", default_value = ".askbend/git_source/")]
+    pub git_checkout_dir: String,
+
+    /// Loaders of the files, the first one whose glob matches the file path is used.
+    #[clap(skip = default_loaders())]
+    pub loaders: Vec<QALoaderConfig>,
+    /// Glob patterns of the files not to load.
+    #[clap(skip = default_ignored_paths())]
+    pub ignored_paths: Vec<String>,
+    /// Base url of the docs site, the sources are `<source_base_url>/<slug>` for the pages with a slug.
+    #[clap(long = "source_base_url", default_value_t)]
+    pub source_base_url: String,
+    /// Directory of the docs under `path` or `git_subdir`, its first level directories are the
+    /// doc sections of the filters.
+    #[clap(long = "docs_root", default_value_t)]
+    pub docs_root: String,
+    /// Product version of the docs, for the pages without a `version` in the front matter.
+    #[clap(long = "docs_version", default_value_t)]
+    pub docs_version: String,
+
+    // chunking of the files, the code and text files are split by lines
+    #[clap(long = "chunk_size", default_value_t = 400)]
+    pub chunk_size: usize,
+    /// Trailing size of a chunk repeated at the start of the next chunk of the same section.
+    #[clap(long = "chunk_overlap", default_value_t)]
+    pub chunk_overlap: usize,
+    /// Chunks smaller than this are merged into their neighbour, 0 to keep all chunks.
+    #[clap(long = "min_chunk_size", default_value_t)]
+    pub min_chunk_size: usize,
+    /// Split the code blocks larger than `chunk_size`, they are never split by default.
+    #[clap(long = "split_code_blocks", default_value_t)]
+    pub split_code_blocks: bool,
+    /// Prepend the `title > headings` path of the chunk to its content before embedding.
+    #[clap(long = "prepend_headings", default_value_t)]
+    pub prepend_headings: bool,
+
+    // deduplication of the chunks
+    /// Duplicate chunks detection <off|exact|near>.
+    #[clap(long = "dedup", default_value = "near")]
+    pub dedup: String,
+    /// Min similarity percentage of the word shingles of the near duplicate chunks.
+    #[clap(long = "dedup_similarity", default_value_t = 90)]
+    pub dedup_similarity: usize,
+    /// Which chunk of the duplicates is kept <first|shortest_path>.
+    #[clap(long = "dedup_keep", default_value = "first")]
+    pub dedup_keep: String,
+    /// Glob patterns of the paths whose chunks are kept over their duplicates elsewhere.
+    #[clap(skip)]
+    pub dedup_prefer_paths: Vec<String>,
+
     // database
     #[clap(long = "database", default_value_t)]
     pub database: String,
@@ -41,21 +104,131 @@ pub struct QAConfig {
     // query
     #[clap(long = "top", default_value_t = 2)]
     pub top: usize,
+    /// Rewrite the question into search queries with the LLM, and retrieve for each of them.
+    #[clap(long = "query_rewrite", default_value_t)]
+    pub query_rewrite: bool,
+    /// Max rewrites of the question, besides the question and its SQL keywords.
+    #[clap(long = "query_rewrite_max", default_value_t = 3)]
+    pub query_rewrite_max: usize,
+    /// Reranker of the vector search candidates <off|lexical|llm>.
+    #[clap(long = "rerank", default_value = "off")]
+    pub rerank: String,
+    /// Candidates fetched from the vector search to rerank, the best `top` ones are kept.
+    #[clap(long = "rerank_candidates", default_value_t = 20)]
+    pub rerank_candidates: usize,
+    /// Token budget of the contexts in the prompt, the lowest ranked chunks are shortened or dropped.
+    #[clap(long = "context_max_tokens", default_value_t = 2000)]
+    pub context_max_tokens: usize,
+    /// Token limit of the whole prompt, the contexts get what the instructions and question leave.
+    #[clap(long = "prompt_max_tokens", default_value_t = 3000)]
+    pub prompt_max_tokens: usize,
+    /// How to shorten the chunk overflowing the budget <truncate|summarize>.
+    #[clap(long = "context_overflow", default_value = "truncate")]
+    pub context_overflow: String,
+    /// Replace the chunks with their whole parent section while the budget allows.
+    #[clap(long = "context_parent_section", default_value_t)]
+    pub context_parent_section: bool,
+    /// Adjacent chunks on each side added to a chunk while the budget allows, 0 to disable.
+    #[clap(long = "context_neighbors", default_value_t = 0)]
+    pub context_neighbors: usize,
+    /// Instructions of the answer prompt.
+    #[clap(skip = default_instructions())]
+    pub instructions: Vec<String>,
+
+    // collections
+    /// Collection to rebuild, the `[qa]` one if empty.
+    #[clap(long = "collection", default_value_t)]
+    pub collection: String,
+    /// Named knowledge bases served besides the `[qa]` one.
+    #[clap(skip)]
+    pub collections: Vec<QACollectionConfig>,
+    /// Weight percentage of the chunks of the collection in the federated search.
+    #[clap(long = "search_weight", default_value_t = 100)]
+    pub search_weight: usize,
 
     // rebuild
     #[clap(long = "rebuild", default_value_t)]
     pub rebuild: bool,
+    /// Chunks embedded and inserted per batch.
+    #[clap(long = "embedding_batch_size", default_value_t = 100)]
+    pub embedding_batch_size: usize,
+    /// Batches embedded at the same time.
+    #[clap(long = "embedding_concurrency", default_value_t = 4)]
+    pub embedding_concurrency: usize,
+    /// Retries of a failed batch, the delay doubles after each retry.
+    #[clap(long = "embedding_max_retries", default_value_t = 3)]
+    pub embedding_max_retries: usize,
+    #[clap(long = "embedding_retry_delay_ms", default_value_t = 1000)]
+    pub embedding_retry_delay_ms: u64,
+    /// Batches embedded by the rebuild, an interrupted rebuild resumes from it.
+    #[clap(
+        long = "rebuild_checkpoint",
+        default_value = ".askbend/rebuild_checkpoint.json"
+    )]
+    pub rebuild_checkpoint: String,
+    /// With `--rebuild`, load and split the corpus and report its statistics without embedding.
+    #[clap(long = "dry-run", default_value_t)]
+    pub dry_run: bool,
+
+    // eval
+    /// JSONL golden set to evaluate the answers of, the evaluation runs instead of the server.
+    /// The questions are embedded by Databend with the `dsn`, whatever the `eval_llm`.
+    #[clap(long = "eval", default_value_t)]
+    pub eval: String,
+    /// JSON report of the evaluation.
+    #[clap(long = "eval_report", default_value = "data/eval_report.json")]
+    pub eval_report: String,
+    /// LLM of the evaluation <databend|extractive>, `extractive` answers with the contexts locally
+    /// but the search still needs the Databend embeddings.
+    #[clap(long = "eval_llm", default_value = "databend")]
+    pub eval_llm: String,
 }
 
 impl Debug for QAConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("QAConfig")
             .field("path", &self.path)
+            .field("git_url", &self.git_url)
+            .field("git_branch", &self.git_branch)
+            .field("git_subdir", &self.git_subdir)
+            .field("git_checkout_dir", &self.git_checkout_dir)
+            .field("loaders", &self.loaders)
+            .field("ignored_paths", &self.ignored_paths)
+            .field("source_base_url", &self.source_base_url)
+            .field("docs_root", &self.docs_root)
+            .field("docs_version", &self.docs_version)
+            .field("chunk_size", &self.chunk_size)
+            .field("chunk_overlap", &self.chunk_overlap)
+            .field("min_chunk_size", &self.min_chunk_size)
+            .field("split_code_blocks", &self.split_code_blocks)
+            .field("prepend_headings", &self.prepend_headings)
+            .field("dedup", &self.dedup)
+            .field("dedup_similarity", &self.dedup_similarity)
+            .field("dedup_keep", &self.dedup_keep)
+            .field("dedup_prefer_paths", &self.dedup_prefer_paths)
             .field("database", &self.database)
             .field("table", &self.table)
             .field("answer_table", &self.answer_table)
             .field("dsn", &"******")
             .field("top", &self.top)
+            .field("query_rewrite", &self.query_rewrite)
+            .field("query_rewrite_max", &self.query_rewrite_max)
+            .field("rerank", &self.rerank)
+            .field("rerank_candidates", &self.rerank_candidates)
+            .field("context_max_tokens", &self.context_max_tokens)
+            .field("prompt_max_tokens", &self.prompt_max_tokens)
+            .field("context_overflow", &self.context_overflow)
+            .field("context_parent_section", &self.context_parent_section)
+            .field("context_neighbors", &self.context_neighbors)
+            .field("instructions", &self.instructions)
+            .field("collection", &self.collection)
+            .field("collections", &self.collections)
+            .field("search_weight", &self.search_weight)
+            .field("embedding_batch_size", &self.embedding_batch_size)
+            .field("embedding_concurrency", &self.embedding_concurrency)
+            .field("embedding_max_retries", &self.embedding_max_retries)
+            .field("embedding_retry_delay_ms", &self.embedding_retry_delay_ms)
+            .field("rebuild_checkpoint", &self.rebuild_checkpoint)
             .finish()
     }
 }
@@ -64,12 +237,238 @@ impl Default for QAConfig {
     fn default() -> Self {
         QAConfig {
             path: "data/".to_string(),
+            git_url: "".to_string(),
+            git_branch: "main".to_string(),
+            git_subdir: "".to_string(),
+            git_checkout_dir: ".askbend/git_source/".to_string(),
+            loaders: default_loaders(),
+            ignored_paths: default_ignored_paths(),
+            source_base_url: "".to_string(),
+            docs_root: "".to_string(),
+            docs_version: "".to_string(),
+            chunk_size: 400,
+            chunk_overlap: 0,
+            min_chunk_size: 0,
+            split_code_blocks: false,
+            prepend_headings: false,
+            dedup: "near".to_string(),
+            dedup_similarity: 90,
+            dedup_keep: "first".to_string(),
+            dedup_prefer_paths: vec![],
             database: "".to_string(),
             table: "".to_string(),
             answer_table: "".to_string(),
             dsn: "".to_string(),
             top: 2,
+            query_rewrite: false,
+            query_rewrite_max: 3,
+            rerank: "off".to_string(),
+            rerank_candidates: 20,
+            context_max_tokens: 2000,
+            prompt_max_tokens: 3000,
+            context_overflow: "truncate".to_string(),
+            context_parent_section: false,
+            context_neighbors: 0,
+            instructions: default_instructions(),
+            collection: "".to_string(),
+            collections: vec![],
+            search_weight: 100,
             rebuild: false,
+            embedding_batch_size: 100,
+            embedding_concurrency: 4,
+            embedding_max_retries: 3,
+            embedding_retry_delay_ms: 1000,
+            rebuild_checkpoint: ".askbend/rebuild_checkpoint.json".to_string(),
+            dry_run: false,
+            eval: "".to_string(),
+            eval_report: "data/eval_report.json".to_string(),
+            eval_llm: "databend".to_string(),
         }
     }
 }
+
+/// Name of the `[qa]` collection.
+pub const DEFAULT_COLLECTION: &str = "default";
+
+impl QAConfig {
+    /// The config of the named collection, the `[qa]` one if the name is empty or `default`.
+    pub fn collection_config(&self, name: &str) -> Result<QAConfig> {
+        if name.is_empty() || name == DEFAULT_COLLECTION {
+            return Ok(self.clone());
+        }
+        let collection = match self.collections.iter().find(|x| x.name == name) {
+            Some(collection) => collection,
+            None => bail!(
+                "unknown collection: {}, expect one of {:?}",
+                name,
+                self.collection_names()
+            ),
+        };
+
+        let mut conf = self.clone();
+        conf.collection = name.to_string();
+        // The source is the collection's own, never the one of `[qa]`.
+        conf.path = collection.path.clone();
+        conf.git_url = collection.git_url.clone();
+        conf.git_branch = collection.git_branch.clone();
+        if conf.git_branch.is_empty() {
+            conf.git_branch = QAConfig::default().git_branch;
+        }
+        conf.git_subdir = collection.git_subdir.clone();
+        conf.source_base_url = collection.source_base_url.clone();
+        conf.docs_root = collection.docs_root.clone();
+
+        let overrides = [
+            (&mut conf.database, &collection.database),
+            (&mut conf.table, &collection.table),
+            (&mut conf.answer_table, &collection.answer_table),
+            (&mut conf.docs_version, &collection.docs_version),
+        ];
+        for (field, value) in overrides {
+            if !value.is_empty() {
+                *field = value.clone();
+            }
+        }
+        if collection.top > 0 {
+            conf.top = collection.top;
+        }
+        if !collection.instructions.is_empty() {
+            conf.instructions = collection.instructions.clone();
+        }
+        if collection.search_weight > 0 {
+            conf.search_weight = collection.search_weight;
+        }
+
+        // Each collection is rebuilt on its own, with its own checkout and checkpoint.
+        conf.git_checkout_dir =
+            format!("{}/{}/", self.git_checkout_dir.trim_end_matches('/'), name);
+        let checkpoint = Path::new(&self.rebuild_checkpoint);
+        let stem = checkpoint.file_stem().unwrap_or_default().to_string_lossy();
+        let file = match checkpoint.extension() {
+            Some(ext) => format!("{}.{}.{}", stem, name, ext.to_string_lossy()),
+            None => format!("{}.{}", stem, name),
+        };
+        conf.rebuild_checkpoint = checkpoint.with_file_name(file).display().to_string();
+        Ok(conf)
+    }
+
+    /// The `source_base_url` of the collection, empty for an unknown collection.
+    pub fn collection_base_url(&self, name: &str) -> String {
+        self.collection_config(name)
+            .map(|x| x.source_base_url)
+            .unwrap_or_default()
+    }
+
+    pub fn collection_names(&self) -> Vec<&str> {
+        self.collections.iter().map(|x| x.name.as_str()).collect()
+    }
+
+    /// The collections must have unique names and tables.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = vec![];
+        let mut tables = vec![format!("{}.{}", self.database, self.table)];
+        for (i, collection) in self.collections.iter().enumerate() {
+            if collection.name.is_empty() {
+                errors.push(format!("collection #{}: missing name", i));
+                continue;
+            }
+            if collection.name == DEFAULT_COLLECTION {
+                errors.push(format!(
+                    "{:?}: reserved name of the [qa] collection",
+                    collection.name
+                ));
+                continue;
+            }
+            if self.collections[..i]
+                .iter()
+                .any(|x| x.name == collection.name)
+            {
+                errors.push(format!("{:?}: duplicate name", collection.name));
+                continue;
+            }
+            if collection.path.is_empty() && collection.git_url.is_empty() {
+                errors.push(format!("{:?}: missing path or git_url", collection.name));
+            }
+            let conf = self.collection_config(&collection.name)?;
+            let table = format!("{}.{}", conf.database, conf.table);
+            if conf.table.is_empty() {
+                errors.push(format!("{:?}: missing table", collection.name));
+            } else if tables.contains(&table) {
+                errors.push(format!(
+                    "{:?}: table {} is used by another collection",
+                    collection.name, table
+                ));
+            }
+            tables.push(table);
+        }
+        if !errors.is_empty() {
+            bail!("invalid qa collections:\n  {}", errors.join("\n  "));
+        }
+        Ok(())
+    }
+}
+
+/// A named knowledge base, `[[qa.collections]]` in the config file.
+/// The source fields `path`, `git_*`, `source_base_url` and `docs_root` are the collection's own,
+/// the other empty fields are inherited from `[qa]`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct QACollectionConfig {
+    pub name: String,
+    pub path: String,
+    pub git_url: String,
+    pub git_branch: String,
+    pub git_subdir: String,
+    pub database: String,
+    pub table: String,
+    pub answer_table: String,
+    pub source_base_url: String,
+    pub docs_root: String,
+    pub docs_version: String,
+    /// `qa.top` if 0.
+    pub top: usize,
+    /// `qa.instructions` if empty.
+    pub instructions: Vec<String>,
+    /// `qa.search_weight` if 0.
+    pub search_weight: usize,
+}
+
+/// Loader of the files matching the glob, `[[qa.loaders]]` in the config file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct QALoaderConfig {
+    pub glob: String,
+    /// Loader name <markdown|mdx|json|code|text>.
+    pub loader: String,
+}
+
+impl Default for QALoaderConfig {
+    fn default() -> Self {
+        QALoaderConfig {
+            glob: "".to_string(),
+            loader: "text".to_string(),
+        }
+    }
+}
+
+fn default_loaders() -> Vec<QALoaderConfig> {
+    vec![QALoaderConfig {
+        glob: "**/*.md".to_string(),
+        loader: "markdown".to_string(),
+    }]
+}
+
+fn default_instructions() -> Vec<String> {
+    vec![
+        "Present your answer in markdown format, including code snippets if have, format the code snippets with SQL type if necessary.".to_string(),
+        "Do not include any links or external references in your response.\n".to_string(),
+        "Do not change the code snippets.\n".to_string(),
+        "Do not change the SQL syntax, please don't make up the function.\n".to_string(),
+        "Do not change explain any code snippets.\n".to_string(),
+        "Make the whole answer as short as possible to keep the code snippets.\n".to_string(),
+    ]
+}
+
+fn default_ignored_paths() -> Vec<String> {
+    vec!["**/.git/**".to_string(), "**/node_modules/**".to_string()]
+}
@@ -0,0 +1,519 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::SecondsFormat;
+use chrono::Utc;
+use http::header::HeaderMap;
+use http::header::HeaderValue;
+use http::header::ACCEPT;
+use http::Method;
+use http::StatusCode;
+use llmchain::Document;
+use llmchain::Documents;
+use log::info;
+use log::warn;
+use octocrab::models::issues::Comment;
+use octocrab::models::pulls::PullRequest;
+use octocrab::models::AppId;
+use octocrab::service::middleware::retry::RetryConfig;
+use octocrab::Octocrab;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+use crate::configs::GithubConfig;
+use crate::configs::GithubRepoConfig;
+
+/// The rate limit state from the last GitHub API response.
+#[derive(Default)]
+struct RateLimit {
+    remaining: Option<u64>,
+    // Epoch seconds when the rate limit window resets.
+    reset: Option<i64>,
+}
+
+/// GitHub API client shared by all the repo scan tasks.
+///
+/// Authenticates with the personal token, or as a GitHub App if `app_id` is set,
+/// in which case each repo is accessed with the token of its app installation.
+/// `api_base_url` points the client to a GitHub Enterprise server.
+///
+/// The requests honor the `X-RateLimit-*` and `Retry-After` headers shared by all the repos,
+/// rate limited and failed requests are retried with exponential backoff.
+#[derive(Clone)]
+pub struct GithubClient {
+    octo: Octocrab,
+    is_app: bool,
+    installations: Arc<Mutex<HashMap<String, Octocrab>>>,
+    bot_login: Arc<Mutex<Option<String>>>,
+    rate_limit: Arc<Mutex<RateLimit>>,
+    max_retries: usize,
+    retry_delay: Duration,
+}
+
+impl GithubClient {
+    pub fn create(conf: &GithubConfig) -> Result<Self> {
+        let mut builder = Octocrab::builder();
+        // The requests are retried by `GithubRepoClient::request` which honors the rate limits.
+        builder.add_retry_config(RetryConfig::None);
+        if !conf.api_base_url.is_empty() {
+            builder = builder
+                .base_uri(conf.api_base_url.as_str())
+                .with_context(|| format!("invalid github api base url: {}", conf.api_base_url))?;
+        }
+
+        let is_app = conf.app_id != 0;
+        let builder = if is_app {
+            let pem = std::fs::read(&conf.app_private_key_path).with_context(|| {
+                format!(
+                    "failed to read github app private key: {}",
+                    conf.app_private_key_path
+                )
+            })?;
+            let key = jsonwebtoken::EncodingKey::from_rsa_pem(&pem)
+                .context("invalid github app private key, expect RSA PEM")?;
+            builder.app(AppId(conf.app_id), key)
+        } else {
+            builder.personal_token(conf.github_token.clone())
+        };
+        let octo = builder.build().context("failed to build github client")?;
+
+        Ok(GithubClient {
+            octo,
+            is_app,
+            installations: Arc::new(Mutex::new(HashMap::new())),
+            bot_login: Arc::new(Mutex::new(None)),
+            rate_limit: Arc::new(Mutex::new(RateLimit::default())),
+            max_retries: conf.max_retries,
+            retry_delay: Duration::from_millis(conf.retry_delay_ms),
+        })
+    }
+
+    /// The client to access the repo.
+    pub async fn repo(&self, owner: &str, repo: &str) -> Result<GithubRepoClient> {
+        let octo = self.repo_octo(owner, repo).await?;
+        Ok(GithubRepoClient {
+            client: self.clone(),
+            octo,
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        })
+    }
+
+    /// Run the step, retry with exponential backoff if it fails.
+    /// Only for the steps without writes, the writes are not idempotent.
+    pub async fn retry<T, F, Fut>(&self, name: &str, step: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match step().await {
+                Ok(v) => return Ok(v),
+                Err(e) if attempt < self.max_retries => {
+                    let delay = self.backoff(attempt);
+                    warn!(
+                        "{} failed, retry {}/{} in {:?}: {:?}",
+                        name,
+                        attempt + 1,
+                        self.max_retries,
+                        delay,
+                        e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// The login of the bot posting the comments, the token user or `<app-slug>[bot]` of the app.
+    pub async fn bot_login(&self) -> Result<String> {
+        let mut bot_login = self.bot_login.lock().await;
+        if let Some(login) = bot_login.as_ref() {
+            return Ok(login.clone());
+        }
+
+        let login = if self.is_app {
+            let app: serde_json::Value = self.octo.get("/app", None::<&()>).await?;
+            app["slug"]
+                .as_str()
+                .map(|x| format!("{}[bot]", x))
+                .ok_or_else(|| anyhow!("github app has no slug"))?
+        } else {
+            let user: serde_json::Value = self.octo.get("/user", None::<&()>).await?;
+            user["login"]
+                .as_str()
+                .map(|x| x.to_string())
+                .ok_or_else(|| anyhow!("github token user has no login"))?
+        };
+        info!("github bot login: {}", login);
+        *bot_login = Some(login.clone());
+        Ok(login)
+    }
+
+    async fn repo_octo(&self, owner: &str, repo: &str) -> Result<Octocrab> {
+        if !self.is_app {
+            return Ok(self.octo.clone());
+        }
+
+        let key = format!("{}/{}", owner, repo);
+        let mut installations = self.installations.lock().await;
+        if let Some(octo) = installations.get(&key) {
+            return Ok(octo.clone());
+        }
+
+        let installation = self
+            .octo
+            .apps()
+            .get_repository_installation(owner, repo)
+            .await
+            .with_context(|| format!("github app is not installed on {}", key))?;
+        info!("repo {} uses app installation {}", key, installation.id);
+        // The installation client requests and refreshes its token on demand.
+        let octo = self.octo.installation(installation.id);
+        installations.insert(key, octo.clone());
+        Ok(octo)
+    }
+
+    fn backoff(&self, attempt: usize) -> Duration {
+        let delay = self.retry_delay * 2u32.saturating_pow(attempt as u32);
+        delay.min(Duration::from_secs(300))
+    }
+
+    /// Wait for the rate limit window to reset if it's exhausted.
+    async fn wait_rate_limit(&self) {
+        let wait = {
+            let rate_limit = self.rate_limit.lock().await;
+            match (rate_limit.remaining, rate_limit.reset) {
+                (Some(0), Some(reset)) => reset - Utc::now().timestamp() + 1,
+                _ => 0,
+            }
+        };
+        if wait > 0 {
+            warn!("GitHub rate limit exhausted, wait {}s for reset", wait);
+            sleep(Duration::from_secs(wait as u64)).await;
+        }
+    }
+
+    async fn update_rate_limit(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse::<i64>().ok())
+        };
+        if let Some(remaining) = header("x-ratelimit-remaining") {
+            let mut rate_limit = self.rate_limit.lock().await;
+            rate_limit.remaining = Some(remaining.max(0) as u64);
+            rate_limit.reset = header("x-ratelimit-reset");
+        }
+    }
+
+    /// The delay before retrying a rate limited response, None if it's not rate limited.
+    fn rate_limit_delay(
+        &self,
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+        attempt: usize,
+    ) -> Option<Duration> {
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.parse::<i64>().ok())
+        };
+        if let Some(retry_after) = header("retry-after") {
+            return Some(Duration::from_secs(retry_after.max(1) as u64));
+        }
+        if header("x-ratelimit-remaining") == Some(0) {
+            let reset = header("x-ratelimit-reset").unwrap_or_default();
+            let wait = (reset - Utc::now().timestamp() + 1).max(1);
+            return Some(Duration::from_secs(wait as u64));
+        }
+        // Secondary rate limits may come without any header.
+        if body.to_lowercase().contains("rate limit") {
+            return Some(self.backoff(attempt));
+        }
+        None
+    }
+}
+
+/// GitHub API client of one repo.
+#[derive(Clone)]
+pub struct GithubRepoClient {
+    client: GithubClient,
+    octo: Octocrab,
+    pub owner: String,
+    pub repo: String,
+}
+
+impl GithubRepoClient {
+    pub async fn bot_login(&self) -> Result<String> {
+        self.client.bot_login().await
+    }
+
+    pub async fn list_open_pulls(&self) -> Result<Vec<PullRequest>> {
+        let route = format!(
+            "/repos/{}/{}/pulls?state=open&per_page=100&page=1",
+            self.owner, self.repo
+        );
+        self.get_json(&route).await
+    }
+
+    /// List the comments of the PR, all the comments if `since` is None.
+    pub async fn list_comments(
+        &self,
+        pull_id: u64,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Comment>> {
+        let route = format!(
+            "/repos/{}/{}/issues/{}/comments?per_page=100",
+            self.owner, self.repo, pull_id
+        );
+        if let Some(since) = since {
+            let route = format!(
+                "{}&page=1&since={}",
+                route,
+                since.to_rfc3339_opts(SecondsFormat::Secs, true)
+            );
+            return self.get_json(&route).await;
+        }
+
+        let mut comments = vec![];
+        for page in 1.. {
+            let items: Vec<Comment> = self.get_json(&format!("{}&page={}", route, page)).await?;
+            let len = items.len();
+            comments.extend(items);
+            if len < 100 {
+                break;
+            }
+        }
+        Ok(comments)
+    }
+
+    pub async fn create_reaction(&self, comment_id: u64, content: &str) -> Result<()> {
+        let route = format!(
+            "/repos/{}/{}/issues/comments/{}/reactions",
+            self.owner, self.repo, comment_id
+        );
+        let body = serde_json::json!({ "content": content });
+        self.request(Method::POST, &route, Some(&body), None)
+            .await?
+            .ok_or_else(|| anyhow!("comment {} not found", comment_id))?;
+        Ok(())
+    }
+
+    pub async fn create_comment(&self, pull_id: u64, body: &str) -> Result<()> {
+        let route = format!(
+            "/repos/{}/{}/issues/{}/comments",
+            self.owner, self.repo, pull_id
+        );
+        let body = serde_json::json!({ "body": body });
+        self.request(Method::POST, &route, Some(&body), None)
+            .await?
+            .ok_or_else(|| anyhow!("PR {} not found", pull_id))?;
+        Ok(())
+    }
+
+    pub async fn update_comment(&self, comment_id: u64, body: &str) -> Result<()> {
+        let route = format!(
+            "/repos/{}/{}/issues/comments/{}",
+            self.owner, self.repo, comment_id
+        );
+        let body = serde_json::json!({ "body": body });
+        self.request(Method::PATCH, &route, Some(&body), None)
+            .await?
+            .ok_or_else(|| anyhow!("comment {} not found", comment_id))?;
+        Ok(())
+    }
+
+    pub async fn create_review<B: Serialize>(&self, pull_id: u64, review: &B) -> Result<()> {
+        let route = format!(
+            "/repos/{}/{}/pulls/{}/reviews",
+            self.owner, self.repo, pull_id
+        );
+        self.request(Method::POST, &route, Some(review), None)
+            .await?
+            .ok_or_else(|| anyhow!("PR {} not found", pull_id))?;
+        Ok(())
+    }
+
+    /// Load the diff of the PR as one document.
+    pub async fn load_pr_diff(&self, pull_id: u64) -> Result<Documents> {
+        let route = format!("/repos/{}/{}/pulls/{}", self.owner, self.repo, pull_id);
+        let diff = self
+            .request(
+                Method::GET,
+                &route,
+                None::<&()>,
+                Some("application/vnd.github.v3.diff"),
+            )
+            .await?
+            .ok_or_else(|| anyhow!("PR {} not found", pull_id))?;
+        info!(
+            "Loaded PR {}/{}#{}, diff_len {}",
+            self.owner,
+            self.repo,
+            pull_id,
+            diff.len()
+        );
+
+        let path = format!("{}/{}/pull/{}", self.owner, self.repo, pull_id);
+        Ok(Documents::from(vec![Document::create(&path, &diff)]))
+    }
+
+    /// Load the repo config file from the default branch, None if not exists.
+    pub async fn load_repo_config(&self, path: &str) -> Result<Option<GithubRepoConfig>> {
+        let route = format!("/repos/{}/{}/contents/{}", self.owner, self.repo, path);
+        let content = match self
+            .request(
+                Method::GET,
+                &route,
+                None::<&()>,
+                Some("application/vnd.github.raw"),
+            )
+            .await?
+        {
+            Some(content) => content,
+            None => return Ok(None),
+        };
+
+        let repo_config: GithubRepoConfig = toml::from_str(&content).with_context(|| {
+            format!(
+                "invalid repo config {} in {}/{}",
+                path, self.owner, self.repo
+            )
+        })?;
+        repo_config.validate()?;
+        Ok(Some(repo_config))
+    }
+
+    /// Check if the user is an active member of the team, `team` format as `org/team-slug`.
+    pub async fn is_team_member(&self, team: &str, user: &str) -> Result<bool> {
+        let (org, slug) = team
+            .split_once('/')
+            .with_context(|| format!("invalid team: {}, expect org/team-slug", team))?;
+        let route = format!("/orgs/{}/teams/{}/memberships/{}", org, slug, user);
+        let membership = match self.request(Method::GET, &route, None::<&()>, None).await? {
+            Some(membership) => membership,
+            None => return Ok(false),
+        };
+        let membership: serde_json::Value = serde_json::from_str(&membership)?;
+        Ok(membership["state"] == "active")
+    }
+
+    async fn get_json<R: DeserializeOwned>(&self, route: &str) -> Result<R> {
+        let body = self
+            .request(Method::GET, route, None::<&()>, None)
+            .await?
+            .ok_or_else(|| anyhow!("{} not found", route))?;
+        serde_json::from_str(&body).with_context(|| format!("invalid response of {}", route))
+    }
+
+    /// Send the request and return the response body, None if the resource is not found.
+    /// Rate limited responses are retried, they are not written. Server and transport errors
+    /// are only retried for GET and PATCH, a failed POST may have been written and would be
+    /// posted twice.
+    async fn request<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        route: &str,
+        body: Option<&B>,
+        accept: Option<&str>,
+    ) -> Result<Option<String>> {
+        let client = &self.client;
+        let mut attempt = 0;
+        loop {
+            client.wait_rate_limit().await;
+
+            let response = match method {
+                Method::GET => {
+                    let mut headers = HeaderMap::new();
+                    if let Some(accept) = accept {
+                        headers.insert(ACCEPT, HeaderValue::from_str(accept)?);
+                    }
+                    self.octo._get_with_headers(route, Some(headers)).await
+                }
+                Method::POST => self.octo._post(route, body).await,
+                Method::PATCH => self.octo._patch(route, body).await,
+                _ => return Err(anyhow!("unsupported method: {}", method)),
+            };
+            let response = match response {
+                Ok(response) => response,
+                Err(e) if attempt < client.max_retries && method != Method::POST => {
+                    let delay = client.backoff(attempt);
+                    warn!("{} {} error, retry in {:?}: {}", method, route, delay, e);
+                    sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let status = response.status();
+            let headers = response.headers().clone();
+            client.update_rate_limit(&headers).await;
+            let text = self.octo.body_to_string(response).await?;
+
+            if status.is_success() {
+                return Ok(Some(text));
+            }
+            if status == StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            if attempt < client.max_retries {
+                let delay = match client.rate_limit_delay(status, &headers, &text, attempt) {
+                    Some(delay) => Some(delay),
+                    None if status.is_server_error() && method != Method::POST => {
+                        Some(client.backoff(attempt))
+                    }
+                    None => None,
+                };
+                if let Some(delay) = delay {
+                    warn!(
+                        "{} {} responds {}, retry {}/{} in {:?}",
+                        method,
+                        route,
+                        status,
+                        attempt + 1,
+                        client.max_retries,
+                        delay
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+            }
+            return Err(anyhow!("{} {} failed: {}, {}", method, route, status, text));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Commands that can be triggered from a pull request comment.
+///
+/// - `askbend:summary` or `/askbend summary`: post a summary of the PR.
+/// - `askbend:review` or `/askbend review`: post inline review comments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GithubCommand {
+    Summary,
+    Review,
+}
+
+impl GithubCommand {
+    /// Parse a command from the comment body, return None if the comment is not a command.
+    pub fn parse(body: &str) -> Option<Self> {
+        let body = body.trim();
+        let name = if let Some(name) = body.strip_prefix("askbend:") {
+            name
+        } else if let Some(name) = body
+            .strip_prefix("/askbend")
+            .filter(|x| x.starts_with(char::is_whitespace))
+        {
+            name
+        } else {
+            return None;
+        };
+
+        Self::from_name(name.trim())
+    }
+
+    /// The command of the name, eg: `summary`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "summary" => Some(GithubCommand::Summary),
+            "review" => Some(GithubCommand::Review),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GithubCommand::Summary => "summary",
+            GithubCommand::Review => "review",
+        }
+    }
+}
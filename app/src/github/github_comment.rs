@@ -13,233 +13,379 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
 use std::time::Duration;
 
 use anyhow::Result;
-use chrono::DateTime;
 use chrono::Utc;
-use llmchain::DatabendLLM;
-use llmchain::DocumentLoader;
-use llmchain::DocumentPath;
-use llmchain::DocumentSplitter;
-use llmchain::GithubPRDiffSplitter;
-use llmchain::GithubPRLoader;
-use llmchain::GithubPRSummary;
-use llmchain::Summarize;
 use log::error;
 use log::info;
-use octocrab::params::State;
-use octocrab::Octocrab;
+use octocrab::models::issues::Comment;
+use octocrab::models::pulls::PullRequest;
 use tokio::time::sleep;
-use url::Url;
 
+use crate::configs::GithubConfig;
+use crate::github::GithubClient;
+use crate::github::GithubCommand;
+use crate::github::GithubRepoClient;
+use crate::github::GithubReview;
+use crate::github::GithubSummary;
+use crate::github::ScanCursor;
+use crate::github::SummaryComment;
 use crate::Config;
+use crate::GithubRepoConfig;
 
+#[derive(Clone)]
 pub struct GithubComment {
     conf: Config,
+    client: GithubClient,
+    // `owner/repo#pull` -> the last head sha whose summary was refreshed.
+    summary_shas: Arc<Mutex<HashMap<String, String>>>,
 }
 
 impl GithubComment {
-    pub fn create(conf: &Config) -> Self {
-        GithubComment { conf: conf.clone() }
+    pub fn create(conf: &Config) -> Result<Self> {
+        Ok(GithubComment {
+            conf: conf.clone(),
+            client: GithubClient::create(&conf.github)?,
+            summary_shas: Arc::new(Mutex::new(HashMap::new())),
+        })
     }
 
     pub fn start(&self) {
-        let keywords = "askbend:summary";
-        let conf = self.conf.clone();
+        let this = self.clone();
         tokio::spawn(async move {
-            let mut now = Utc::now();
-            let mut scan_map: HashMap<String, DateTime<Utc>> = HashMap::new();
+            let mut cursor = ScanCursor::create(Utc::now());
             loop {
-                if let Some(repos) = &conf.github.repos {
+                let repos = this.conf.github.all_repos();
+                if !repos.is_empty() {
                     info!("scan repos: {:?}", repos);
+                }
 
-                    for repo in repos.clone() {
-                        let cloned_conf = conf.clone();
-                        let cloned_repo = repo.clone();
-                        let cloned_keywords = keywords.to_string().clone();
-                        let task_now = now;
-                        let map_clone = scan_map.clone();
-                        let task = tokio::spawn(async move {
-                            info!("Scan repo: {} at {}", cloned_repo, task_now);
-
-                            let (owner, repo_name) = match Self::parse_github_repo(&cloned_repo) {
-                                Ok(val) => val,
-                                Err(e) => {
-                                    error!("Failed to parse github repo: {:?}", e);
-                                    return;
-                                }
-                            };
-
-                            let pull_requests = match Self::get_octo(&cloned_conf)
-                                .pulls(&owner, &repo_name)
-                                .list()
-                                .page(1u32)
-                                .per_page(100)
-                                .state(State::Open)
-                                .send()
-                                .await
-                            {
-                                Ok(val) => val,
-                                Err(e) => {
-                                    error!("Failed to get pull requests: {:?}", e);
-                                    return;
-                                }
-                            };
-
-                            let since = *map_clone.get(&repo_name).unwrap_or(&task_now);
-
-                            for pr in pull_requests {
-                                info!(
-                                    "Scan pr {}/{} path:{:?}, title: {:?}, create_at:{:?}",
-                                    owner,
-                                    repo_name,
-                                    pr.html_url.unwrap().path(),
-                                    pr.title,
-                                    pr.created_at
-                                );
-                                let pr_comments = match Self::get_octo(&cloned_conf)
-                                    .issues(&owner, &repo_name)
-                                    .list_comments(pr.number)
-                                    .page(1u32)
-                                    .per_page(100)
-                                    .since(since)
-                                    .send()
-                                    .await
-                                {
-                                    Ok(val) => val,
-                                    Err(e) => {
-                                        error!("Failed to list comments: {:?}", e);
-                                        return;
-                                    }
-                                };
-
-                                let mut comments = pr_comments.items.clone();
-                                comments.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-
-                                for comment in comments {
-                                    info!(
-                                        "Pr number:{}, Comment ID: {}, Body: {:?}, create_at:{:?}, url:{:?}",
-                                        pr.number,
-                                        comment.id,
-                                        comment.body,
-                                        comment.created_at,
-                                        comment.issue_url
-                                    );
-
-                                    if comment.body == Some(cloned_keywords.clone()) {
-                                        match Self::get_octo(&cloned_conf)
-                                            .issues(&owner, &repo_name)
-                                            .create_comment_reaction(
-                                                comment.id,
-                                                octocrab::models::reactions::ReactionContent::PlusOne,
-                                            )
-                                            .await
-                                        {
-                                            Ok(_) => {},
-                                            Err(e) => {
-                                                error!("Failed to create comment reaction: {:?}", e);
-                                                return;
-                                            }
-                                        };
-
-                                        match Self::get_summary(
-                                            &cloned_conf,
-                                            &owner,
-                                            &repo_name,
-                                            pr.number,
-                                        )
-                                        .await
-                                        {
-                                            Ok(summary) => {
-                                                let final_summary = format!(
-                                                    "## PR Summary(By [llmchain.rs](https://github.com/shafishlabs/llmchain.rs)):\n{}",
-                                                    summary
-                                                );
-
-                                                match Self::get_octo(&cloned_conf)
-                                                    .issues(&owner, &repo_name)
-                                                    .create_comment(pr.number, final_summary)
-                                                    .await
-                                                {
-                                                    Ok(_) => {}
-                                                    Err(e) => {
-                                                        error!("Failed to create comment: {:?}", e);
-                                                        return;
-                                                    }
-                                                };
-                                            }
-                                            Err(e) => {
-                                                error!("Failed to get summary: {:?}", e);
-                                            }
-                                        }
-                                    }
-                                }
-                                sleep(Duration::from_millis(100)).await;
-                            }
-                        });
+                for repo in repos {
+                    let scanner = this.clone();
+                    let repo_url = repo.clone();
+                    let mut repo_cursor = cursor.clone();
+                    let task = tokio::spawn(async move {
+                        let result = scanner.scan_repo(&repo_url, &mut repo_cursor).await;
+                        (repo_cursor, result)
+                    });
 
-                        if let Err(e) = task.await {
-                            error!("Task panicked with error: {:?}", e);
+                    match task.await {
+                        // The comments scanned before a failure are kept scanned.
+                        Ok((repo_cursor, result)) => {
+                            cursor = repo_cursor;
+                            if let Err(e) = result {
+                                error!("Failed to scan repo {}: {:?}", repo, e);
+                            }
                         }
-
-                        now = Utc::now();
-                        scan_map.insert(repo.clone(), now);
+                        Err(e) => error!("Task panicked with error: {:?}", e),
                     }
                 }
-                sleep(Duration::from_secs(conf.github.check_in_secs as u64)).await;
+                sleep(Duration::from_secs(this.conf.github.check_in_secs as u64)).await;
             }
         });
     }
 
-    fn get_octo(conf: &Config) -> Octocrab {
-        Octocrab::builder()
-            .personal_token(conf.github.github_token.clone())
-            .build()
-            .unwrap()
+    /// Scan the open PRs of the repo and run the commands commented after the cursor of each PR.
+    /// A failed PR is logged and skipped, the other PRs are still scanned.
+    pub async fn scan_repo(&self, repo_url: &str, cursor: &mut ScanCursor) -> Result<()> {
+        info!("Scan repo: {}", repo_url);
+
+        let (owner, repo) = GithubConfig::parse_repo(repo_url)?;
+        let client = self.client.repo(&owner, &repo).await?;
+        let repo_config = Self::get_repo_config(&self.conf, &client, repo_url).await?;
+        let pull_requests = client.list_open_pulls().await?;
+        cursor.retain(
+            repo_url,
+            &pull_requests.iter().map(|x| x.number).collect::<Vec<_>>(),
+        );
+
+        for pr in pull_requests {
+            info!(
+                "Scan pr {}/{}#{}, title: {:?}, create_at:{:?}",
+                owner, repo, pr.number, pr.title, pr.created_at
+            );
+
+            if self.conf.github.auto_update_summary
+                && repo_config.is_enabled(GithubCommand::Summary.name())
+            {
+                if let Err(e) = self
+                    .refresh_summary(&client, &repo_config, pr.number, &pr.head.sha)
+                    .await
+                {
+                    error!("Failed to refresh summary: {:?}", e);
+                }
+            }
+
+            let triggers = Self::triggers(&client, &repo_config, repo_url, pr.number, cursor);
+            let commands = match triggers.await {
+                Ok(commands) => commands,
+                Err(e) => {
+                    error!("Failed to get the commands of #{}: {:?}", pr.number, e);
+                    continue;
+                }
+            };
+            for command in commands {
+                self.run(&client, &repo_config, &pr, command).await;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        Ok(())
     }
 
-    fn parse_github_repo(url: &str) -> Result<(String, String)> {
-        let parsed_url = Url::parse(url)?;
+    /// The commands triggered by the PR comments not scanned yet, newest first.
+    /// The comments listed are marked scanned in the cursor.
+    /// Commands disabled for the repo or from the users not allowed are skipped,
+    /// the accepted ones are acknowledged with a +1 reaction.
+    pub async fn triggers(
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        repo_url: &str,
+        pull_id: u64,
+        cursor: &mut ScanCursor,
+    ) -> Result<Vec<GithubCommand>> {
+        // The API lists the comments updated since, the edited old ones are skipped.
+        let since = cursor.since(repo_url, pull_id);
+        let mut comments = client.list_comments(pull_id, Some(since)).await?;
+        comments.retain(|x| cursor.is_new(repo_url, pull_id, *x.id, x.created_at));
+        comments.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        for comment in &comments {
+            cursor.advance(repo_url, pull_id, *comment.id, comment.created_at);
+        }
 
-        let mut segments = parsed_url.path_segments().expect("path segments");
+        let mut commands = vec![];
+        for comment in comments {
+            info!(
+                "Pr number:{}, Comment ID: {}, Body: {:?}, create_at:{:?}, url:{:?}",
+                pull_id, comment.id, comment.body, comment.created_at, comment.issue_url
+            );
 
-        let owner = segments.next().expect("owner").to_string();
-        info!("owner: {}", owner);
-        let repo = segments.next().expect("repo").to_string();
-        info!("repo: {}", repo);
+            let command = match comment.body.as_deref().and_then(GithubCommand::parse) {
+                Some(command) => command,
+                None => continue,
+            };
 
-        Ok((owner, repo))
+            if !repo_config.is_enabled(command.name()) {
+                info!(
+                    "Command {} is not enabled for {}",
+                    command.name(),
+                    repo_config.repo
+                );
+                continue;
+            }
+
+            match Self::is_allowed(client, repo_config, &comment.user.login).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!(
+                        "User {} is not allowed to trigger {}",
+                        comment.user.login,
+                        command.name()
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to check user permission: {:?}", e);
+                    continue;
+                }
+            }
+
+            if let Err(e) = client.create_reaction(*comment.id, "+1").await {
+                error!("Failed to create comment reaction: {:?}", e);
+            }
+            commands.push(command);
+        }
+        Ok(commands)
     }
 
-    async fn get_summary(conf: &Config, owner: &str, repo: &str, pull_id: u64) -> Result<String> {
-        info!("get summary for {}/{}#{}", owner, repo, pull_id);
-        let github_token = conf.github.github_token.clone();
-        let databend_dsn = conf.github.databend_dsn.clone();
+    /// Post the summary of the PR head commit.
+    /// The previous summary comment is edited in place if there is one.
+    pub async fn post_summary_comment(
+        client: &GithubRepoClient,
+        pull_id: u64,
+        head_sha: &str,
+        summary: &str,
+    ) -> Result<()> {
+        let body = SummaryComment::render(summary, head_sha);
 
-        let documents = GithubPRLoader::create(owner, repo, &github_token)
-            .load(DocumentPath::from_list(vec![pull_id as usize]))
+        match Self::find_summary_comment(client, pull_id).await? {
+            Some(comment) => {
+                info!(
+                    "update summary comment {} of {}/{}#{}",
+                    comment.id, client.owner, client.repo, pull_id
+                );
+                client.update_comment(*comment.id, &body).await?;
+            }
+            None => {
+                client.create_comment(pull_id, &body).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pr: &PullRequest,
+        command: GithubCommand,
+    ) {
+        match command {
+            GithubCommand::Review => {
+                let review = GithubReview::create(&self.conf);
+                // Only the review is retried, the post is not to post it twice.
+                let request = self
+                    .client
+                    .retry("review", || review.prepare(client, repo_config, pr.number))
+                    .await;
+                let posted = match request {
+                    Ok(request) => GithubReview::post(client, pr.number, &request).await,
+                    Err(e) => Err(e),
+                };
+                match posted {
+                    Ok(n) => {
+                        info!("Post review with {} comments", n);
+                    }
+                    Err(e) => {
+                        error!("Failed to post review: {:?}", e);
+                    }
+                }
+            }
+            GithubCommand::Summary => {
+                if let Err(e) = self
+                    .post_summary(client, repo_config, pr.number, &pr.head.sha)
+                    .await
+                {
+                    error!("Failed to post summary: {:?}", e);
+                }
+            }
+        }
+    }
+
+    /// Summarize the PR and post it, only the summarizing is retried.
+    async fn post_summary(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pull_id: u64,
+        head_sha: &str,
+    ) -> Result<()> {
+        let summary = GithubSummary::create(&self.conf, repo_config);
+        let summary = self
+            .client
+            .retry("summary", || summary.summarize(client, pull_id))
             .await?;
+        Self::post_summary_comment(client, pull_id, head_sha, &summary).await
+    }
+
+    /// Re-summarize the PR if its summary comment was made for an older commit.
+    /// Each head sha is checked once, the comments are only listed after a push.
+    async fn refresh_summary(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pull_id: u64,
+        head_sha: &str,
+    ) -> Result<()> {
+        let key = format!("{}/{}#{}", client.owner, client.repo, pull_id);
+        if self
+            .summary_shas
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|x| x.as_str())
+            == Some(head_sha)
+        {
+            return Ok(());
+        }
+
+        let comment = Self::find_summary_comment(client, pull_id).await?;
+        // A failed summary is not retried until the next push.
+        self.summary_shas
+            .lock()
+            .unwrap()
+            .insert(key, head_sha.to_string());
+        let comment = match comment {
+            Some(comment) => comment,
+            None => return Ok(()),
+        };
 
-        let documents = GithubPRDiffSplitter::create()
-            .with_chunk_size(8000)
-            .split_documents(&documents)
-            .unwrap();
-
-        if documents.tokens() > conf.github.llm_max_tokens {
-            return Ok(format!(
-                "The PR is too large to summarize, tokens: {}, max tokens: {}",
-                documents.tokens(),
-                conf.github.llm_max_tokens
-            ));
+        let summary_sha = comment.body.as_deref().and_then(SummaryComment::parse_sha);
+        if summary_sha == Some(head_sha) {
+            return Ok(());
         }
 
-        let databend_llm = DatabendLLM::create(&databend_dsn);
-        let summary = GithubPRSummary::create(databend_llm);
-        summary.add_documents(&documents).await?;
-        let pr_summary = summary.final_summary().await?;
-        info!("Tokens: {}, Summary: {}", summary.tokens(), pr_summary);
+        info!(
+            "summary of {}/{}#{} is outdated, summary sha:{:?}, head sha:{}",
+            client.owner, client.repo, pull_id, summary_sha, head_sha
+        );
+        self.post_summary(client, repo_config, pull_id, head_sha)
+            .await
+    }
+
+    /// The last summary comment posted by the bot, the other users' comments are ignored.
+    async fn find_summary_comment(
+        client: &GithubRepoClient,
+        pull_id: u64,
+    ) -> Result<Option<Comment>> {
+        let bot_login = client.bot_login().await?;
+        let comments = client.list_comments(pull_id, None).await?;
+
+        Ok(comments.into_iter().rev().find(|x| {
+            x.user.login == bot_login
+                && x.body
+                    .as_deref()
+                    .map(SummaryComment::is_summary)
+                    .unwrap_or(false)
+        }))
+    }
+
+    /// The repo config file in the repo is merged into the repo config, it can't widen the access.
+    async fn get_repo_config(
+        conf: &Config,
+        client: &GithubRepoClient,
+        repo_url: &str,
+    ) -> Result<GithubRepoConfig> {
+        let repo_config = conf.github.repo_config(repo_url);
+        if conf.github.repo_config_path.is_empty() {
+            return Ok(repo_config);
+        }
+
+        let path = &conf.github.repo_config_path;
+        match client.load_repo_config(path).await? {
+            Some(file_config) => {
+                info!(
+                    "Use repo config {} of {}/{}",
+                    path, client.owner, client.repo
+                );
+                Ok(repo_config.merge_file(file_config))
+            }
+            None => Ok(repo_config),
+        }
+    }
 
-        Ok(pr_summary)
+    async fn is_allowed(
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        user: &str,
+    ) -> Result<bool> {
+        if repo_config.allowed_users.is_empty() && repo_config.allowed_teams.is_empty() {
+            return Ok(true);
+        }
+        if repo_config
+            .allowed_users
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(user))
+        {
+            return Ok(true);
+        }
+        for team in &repo_config.allowed_teams {
+            if client.is_team_member(team, user).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
     }
 }
@@ -0,0 +1,89 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+/// The scan position of each PR, only the comments not scanned yet are scanned.
+///
+/// Each PR has its own position, a comment posted on a PR scanned before the newer comments
+/// of the other PRs is not skipped. The comments of the same second as the position are told
+/// apart by their ids.
+#[derive(Debug, Clone)]
+pub struct ScanCursor {
+    start: DateTime<Utc>,
+    // (repo, pull) -> the position of the PR.
+    pulls: HashMap<(String, u64), PullCursor>,
+}
+
+#[derive(Debug, Clone)]
+struct PullCursor {
+    since: DateTime<Utc>,
+    // The comments created at `since` scanned.
+    seen: Vec<u64>,
+}
+
+impl ScanCursor {
+    /// PRs never scanned start from `start`, the comments before it are ignored.
+    pub fn create(start: DateTime<Utc>) -> Self {
+        ScanCursor {
+            start,
+            pulls: HashMap::new(),
+        }
+    }
+
+    /// The creation time of the newest comment scanned of the PR.
+    pub fn since(&self, repo: &str, pull: u64) -> DateTime<Utc> {
+        self.pulls
+            .get(&(repo.to_string(), pull))
+            .map(|x| x.since)
+            .unwrap_or(self.start)
+    }
+
+    /// Whether the comment of the PR is not scanned yet.
+    pub fn is_new(&self, repo: &str, pull: u64, id: u64, created_at: DateTime<Utc>) -> bool {
+        match self.pulls.get(&(repo.to_string(), pull)) {
+            Some(cursor) => {
+                created_at > cursor.since
+                    || (created_at == cursor.since && !cursor.seen.contains(&id))
+            }
+            None => created_at > self.start,
+        }
+    }
+
+    /// Mark the comment of the PR scanned, the cursor never moves backwards.
+    pub fn advance(&mut self, repo: &str, pull: u64, id: u64, created_at: DateTime<Utc>) {
+        let cursor = self
+            .pulls
+            .entry((repo.to_string(), pull))
+            .or_insert_with(|| PullCursor {
+                since: self.start,
+                seen: vec![],
+            });
+        if created_at > cursor.since {
+            cursor.since = created_at;
+            cursor.seen = vec![id];
+        } else if created_at == cursor.since && !cursor.seen.contains(&id) {
+            cursor.seen.push(id);
+        }
+    }
+
+    /// Forget the PRs of the repo not open anymore.
+    pub fn retain(&mut self, repo: &str, open_pulls: &[u64]) {
+        self.pulls
+            .retain(|(x, pull), _| x != repo || open_pulls.contains(pull));
+    }
+}
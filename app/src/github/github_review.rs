@@ -0,0 +1,326 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::DatabendLLM;
+use llmchain::DocumentSplitter;
+use llmchain::GithubPRDiffSplitter;
+use llmchain::Prompt;
+use llmchain::PromptTemplate;
+use llmchain::LLM;
+use log::info;
+use serde::Serialize;
+
+use crate::github::GithubRepoClient;
+use crate::Config;
+use crate::GithubRepoConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReviewSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+impl FromStr for ReviewSeverity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Ok(ReviewSeverity::Low),
+            "medium" => Ok(ReviewSeverity::Medium),
+            "high" => Ok(ReviewSeverity::High),
+            _ => Err(anyhow!("unknown review severity: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewComment {
+    pub severity: ReviewSeverity,
+    pub path: String,
+    pub line: u64,
+    pub body: String,
+}
+
+impl ReviewComment {
+    /// Parse the review comments from the LLM answer, one comment per line:
+    /// `[HIGH] src/lib.rs:12: comment`
+    /// Lines not in this format are ignored.
+    pub fn parse_list(text: &str) -> Vec<ReviewComment> {
+        text.lines().filter_map(Self::parse).collect()
+    }
+
+    fn parse(line: &str) -> Option<ReviewComment> {
+        let line = line.trim().trim_start_matches(['-', '*', ' ']);
+        let rest = line.strip_prefix('[')?;
+        let (severity, rest) = rest.split_once(']')?;
+        let severity = ReviewSeverity::from_str(severity).ok()?;
+
+        let (path, rest) = rest.trim().split_once(':')?;
+        let (line, body) = rest.split_once(':')?;
+        let line = line.trim().parse::<u64>().ok()?;
+        let body = body.trim();
+        if path.is_empty() || body.is_empty() {
+            return None;
+        }
+
+        Some(ReviewComment {
+            severity,
+            path: path.trim().trim_matches('`').to_string(),
+            line,
+            body: body.to_string(),
+        })
+    }
+}
+
+/// The new-side line numbers of a unified diff.
+/// Only these lines can carry an inline review comment.
+pub struct DiffLines {
+    files: HashMap<String, BTreeSet<u64>>,
+    annotated: String,
+}
+
+impl DiffLines {
+    pub fn parse(diff: &str) -> Self {
+        let mut files: HashMap<String, BTreeSet<u64>> = HashMap::new();
+        let mut annotated = vec![];
+        let mut path = String::new();
+        let mut new_line = 0u64;
+        // Remaining old/new lines of the current hunk.
+        let mut old_left = 0u64;
+        let mut new_left = 0u64;
+
+        for line in diff.lines() {
+            if old_left == 0 && new_left == 0 {
+                if let Some(new_path) = line.strip_prefix("+++ ") {
+                    let new_path = new_path.split('\t').next().unwrap_or_default();
+                    path = new_path.strip_prefix("b/").unwrap_or(new_path).to_string();
+                } else if let Some((start, old_count, new_count)) = Self::parse_hunk_header(line) {
+                    new_line = start;
+                    old_left = old_count;
+                    new_left = new_count;
+                }
+                annotated.push(format!("{:>6} {}", "", line));
+                continue;
+            }
+
+            if line.starts_with('+') {
+                files.entry(path.clone()).or_default().insert(new_line);
+                annotated.push(format!("{:>6} {}", new_line, line));
+                new_line += 1;
+                new_left = new_left.saturating_sub(1);
+            } else if line.starts_with('-') {
+                annotated.push(format!("{:>6} {}", "", line));
+                old_left = old_left.saturating_sub(1);
+            } else if line.starts_with('\\') {
+                annotated.push(format!("{:>6} {}", "", line));
+            } else {
+                files.entry(path.clone()).or_default().insert(new_line);
+                annotated.push(format!("{:>6} {}", new_line, line));
+                new_line += 1;
+                old_left = old_left.saturating_sub(1);
+                new_left = new_left.saturating_sub(1);
+            }
+        }
+
+        DiffLines {
+            files,
+            annotated: annotated.join("\n"),
+        }
+    }
+
+    pub fn contains(&self, path: &str, line: u64) -> bool {
+        self.files
+            .get(path)
+            .map(|lines| lines.contains(&line))
+            .unwrap_or(false)
+    }
+
+    /// The diff with the new-side line number in front of each line.
+    pub fn annotated(&self) -> &str {
+        &self.annotated
+    }
+
+    // @@ -1,7 +1,6 @@ hint
+    fn parse_hunk_header(line: &str) -> Option<(u64, u64, u64)> {
+        let rest = line.strip_prefix("@@ -")?;
+        let (ranges, _) = rest.split_once(" @@")?;
+        let (old, new) = ranges.split_once(" +")?;
+        let parse_range = |range: &str| -> Option<(u64, u64)> {
+            match range.split_once(',') {
+                Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+                None => Some((range.parse().ok()?, 1)),
+            }
+        };
+        let (_, old_count) = parse_range(old)?;
+        let (new_start, new_count) = parse_range(new)?;
+        Some((new_start, old_count, new_count))
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct ReviewRequest {
+    body: String,
+    event: String,
+    comments: Vec<ReviewCommentRequest>,
+}
+
+#[derive(Serialize)]
+struct ReviewCommentRequest {
+    path: String,
+    line: u64,
+    side: String,
+    body: String,
+}
+
+pub struct GithubReview {
+    conf: Config,
+}
+
+impl GithubReview {
+    pub fn create(conf: &Config) -> Self {
+        GithubReview { conf: conf.clone() }
+    }
+
+    /// Review the PR diff and post the comments as one pull request review.
+    /// Returns the number of inline comments posted.
+    pub async fn review(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pull_id: u64,
+    ) -> Result<usize> {
+        let request = self.prepare(client, repo_config, pull_id).await?;
+        Self::post(client, pull_id, &request).await
+    }
+
+    /// Review the PR diff without posting, safe to retry.
+    pub(crate) async fn prepare(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pull_id: u64,
+    ) -> Result<ReviewRequest> {
+        let comments = self.get_comments(client, repo_config, pull_id).await?;
+        let min_severity = ReviewSeverity::from_str(&self.conf.github.review_min_severity)?;
+        let comments = Self::select(comments, min_severity, self.conf.github.review_max_comments);
+
+        let body = if comments.is_empty() {
+            "## PR Review(By [llmchain.rs](https://github.com/shafishlabs/llmchain.rs)):\nNo issues found.".to_string()
+        } else {
+            format!(
+                "## PR Review(By [llmchain.rs](https://github.com/shafishlabs/llmchain.rs)):\n{} comment(s) with severity >= {:?}.",
+                comments.len(),
+                min_severity
+            )
+        };
+        Ok(ReviewRequest {
+            body,
+            event: "COMMENT".to_string(),
+            comments: comments
+                .iter()
+                .map(|x| ReviewCommentRequest {
+                    path: x.path.clone(),
+                    line: x.line,
+                    side: "RIGHT".to_string(),
+                    body: format!("**[{:?}]** {}", x.severity, x.body),
+                })
+                .collect(),
+        })
+    }
+
+    /// Post the review, returns the number of inline comments posted.
+    pub(crate) async fn post(
+        client: &GithubRepoClient,
+        pull_id: u64,
+        request: &ReviewRequest,
+    ) -> Result<usize> {
+        client.create_review(pull_id, request).await?;
+        Ok(request.comments.len())
+    }
+
+    /// Keep the comments at or above the severity, most severe first, at most `max_comments`.
+    pub fn select(
+        mut comments: Vec<ReviewComment>,
+        min_severity: ReviewSeverity,
+        max_comments: usize,
+    ) -> Vec<ReviewComment> {
+        comments.retain(|x| x.severity >= min_severity);
+        comments.sort_by(|a, b| b.severity.cmp(&a.severity));
+        comments.truncate(max_comments);
+        comments
+    }
+
+    async fn get_comments(
+        &self,
+        client: &GithubRepoClient,
+        repo_config: &GithubRepoConfig,
+        pull_id: u64,
+    ) -> Result<Vec<ReviewComment>> {
+        info!(
+            "get review for {}/{}#{}",
+            client.owner, client.repo, pull_id
+        );
+        let databend_dsn = self.conf.github.databend_dsn.clone();
+
+        let documents = client.load_pr_diff(pull_id).await?;
+        let documents = GithubPRDiffSplitter::create()
+            .with_chunk_size(repo_config.chunk_size)
+            .with_skips(repo_config.diff_skips(&self.conf.github))
+            .split_documents(&documents)?;
+
+        let template = "You are an expert programmer reviewing a pull request diff. \
+            Point out bugs, security problems, performance problems and unclear code, ignore the style nits.\n\
+            Each diff line is prefixed with its line number in the new file, only comment on numbered lines.\n\
+            Output one issue per line in the format:\n\
+            [HIGH|MEDIUM|LOW] file-path:line-number: comment\n\
+            If there are no issues, output NONE.\n\
+            --------\n\
+            {diff}";
+        let prompt_template = PromptTemplate::create(template, vec!["diff".to_string()]);
+        let databend_llm = DatabendLLM::create(&databend_dsn);
+
+        let mut comments = vec![];
+        for (i, document) in documents.iter().enumerate() {
+            let diff_lines = DiffLines::parse(&document.content);
+            let mut input_variables = HashMap::new();
+            input_variables.insert("diff", diff_lines.annotated());
+            let prompt = prompt_template.format(input_variables)?;
+
+            let result = databend_llm.generate(&prompt).await?;
+            let chunk_comments = ReviewComment::parse_list(&result.generation);
+            info!(
+                "review [{}/{}]: {} comments",
+                i + 1,
+                documents.len(),
+                chunk_comments.len()
+            );
+
+            // GitHub rejects the whole review if a comment is not on a diff line.
+            comments.extend(
+                chunk_comments
+                    .into_iter()
+                    .filter(|x| diff_lines.contains(&x.path, x.line)),
+            );
+        }
+
+        Ok(comments)
+    }
+}
@@ -0,0 +1,324 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use anyhow::Result;
+use llmchain::DatabendLLM;
+use llmchain::Document;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+use llmchain::GithubPRDiffSplitter;
+use llmchain::GithubPRSummary;
+use llmchain::GithubPRSummaryPrompt;
+use llmchain::Prompt;
+use llmchain::Summarize;
+use llmchain::LLM;
+use log::info;
+use tiktoken_rs::r50k_base;
+
+use crate::github::GithubRepoClient;
+use crate::Config;
+use crate::GithubRepoConfig;
+
+const SUMMARY_MARKER: &str = "<!-- askbend:summary";
+/// Min tokens of a truncated group summary in the final summary input.
+const MIN_GROUP_TOKENS: usize = 32;
+
+/// The PR summary comment posted by the bot.
+///
+/// The comment carries a hidden marker with the head commit SHA it was generated from,
+/// so the bot can find and edit its previous summary instead of posting a new one:
+/// `<!-- askbend:summary sha:<sha> -->`
+pub struct SummaryComment;
+
+impl SummaryComment {
+    pub fn render(summary: &str, sha: &str) -> String {
+        let short_sha = &sha[..sha.len().min(7)];
+        format!(
+            "{} sha:{} -->\n## PR Summary(By [llmchain.rs](https://github.com/shafishlabs/llmchain.rs)):\n{}\n\n_Summary of commit {}._",
+            SUMMARY_MARKER, sha, summary, short_sha
+        )
+    }
+
+    pub fn is_summary(body: &str) -> bool {
+        body.starts_with(SUMMARY_MARKER)
+    }
+
+    /// The commit SHA recorded in the summary comment.
+    pub fn parse_sha(body: &str) -> Option<&str> {
+        let rest = body.strip_prefix(SUMMARY_MARKER)?;
+        let (marker, _) = rest.split_once("-->")?;
+        let sha = marker.trim().strip_prefix("sha:")?.trim();
+        if sha.is_empty() { None } else { Some(sha) }
+    }
+}
+
+/// Summarize the PR diff.
+///
+/// The diff is summarized as a whole if it fits in the max tokens, otherwise
+/// it is summarized in map-reduce mode: the file patches are grouped by directory,
+/// each group is summarized separately and the group summaries are merged.
+pub struct GithubSummary {
+    conf: Config,
+    repo_config: GithubRepoConfig,
+}
+
+impl GithubSummary {
+    pub fn create(conf: &Config, repo_config: &GithubRepoConfig) -> Self {
+        GithubSummary {
+            conf: conf.clone(),
+            repo_config: repo_config.clone(),
+        }
+    }
+
+    pub async fn summarize(&self, client: &GithubRepoClient, pull_id: u64) -> Result<String> {
+        info!(
+            "get summary for {}/{}#{}",
+            client.owner, client.repo, pull_id
+        );
+        let databend_llm = DatabendLLM::create(&self.conf.github.databend_dsn);
+        let skips = self.repo_config.diff_skips(&self.conf.github);
+
+        let diff = client.load_pr_diff(pull_id).await?;
+        let documents = GithubPRDiffSplitter::create()
+            .with_chunk_size(self.repo_config.chunk_size)
+            .with_skips(skips.clone())
+            .split_documents(&diff)?;
+
+        let max_tokens = self.repo_config.max_tokens(&self.conf.github);
+        let tokens = documents.tokens();
+        let pr_summary = if tokens <= max_tokens {
+            let summary = GithubPRSummary::create(databend_llm.clone());
+            summary.add_documents(&documents).await?;
+            let pr_summary = summary.final_summary().await?;
+            info!("Tokens: {}, Summary: {}", summary.tokens(), pr_summary);
+            pr_summary
+        } else {
+            info!(
+                "PR tokens {} > max tokens {}, summarize by groups",
+                tokens, max_tokens
+            );
+            // Chunk size 0 makes every file patch a document.
+            let patches = GithubPRDiffSplitter::create()
+                .with_chunk_size(0)
+                .with_skips(skips)
+                .split_documents(&diff)?;
+            self.summarize_groups(databend_llm.clone(), &patches, max_tokens)
+                .await?
+        };
+
+        if self.repo_config.language.is_empty() {
+            return Ok(pr_summary);
+        }
+        let prompt = format!(
+            "Translate the following pull request summary into {}, keep the markdown format, the code and the names unchanged:\n{}",
+            self.repo_config.language, pr_summary
+        );
+        let translated = databend_llm.generate(&prompt).await?;
+        Ok(translated.generation)
+    }
+
+    async fn summarize_groups(
+        &self,
+        llm: std::sync::Arc<DatabendLLM>,
+        patches: &Documents,
+        max_tokens: usize,
+    ) -> Result<String> {
+        let groups = Self::group_patches(patches, self.conf.github.summary_group_depth);
+        let max_groups = self.conf.github.summary_max_groups;
+
+        let mut group_summaries = vec![];
+        let mut skipped_groups = vec![];
+        for (i, (group, group_patches)) in groups.iter().enumerate() {
+            if i >= max_groups {
+                skipped_groups.push(group.clone());
+                continue;
+            }
+
+            let (chunks, dropped) =
+                Self::chunk_patches(group_patches, self.repo_config.chunk_size, max_tokens);
+            info!(
+                "summary group [{}/{}] {}: {} patches, {} chunks, {} dropped",
+                i + 1,
+                groups.len(),
+                group,
+                group_patches.len(),
+                chunks.len(),
+                dropped
+            );
+
+            let summary = GithubPRSummary::create(llm.clone());
+            summary.add_documents(&chunks).await?;
+            let mut group_summary = summary.final_summary().await?;
+            if dropped > 0 {
+                group_summary.push_str(&format!(
+                    "\n\n_{} file(s) are too large to summarize._",
+                    dropped
+                ));
+            }
+            group_summaries.push((group.clone(), group_summary));
+        }
+
+        let mut input_variables = HashMap::new();
+        input_variables.insert("text", "");
+        let template = GithubPRSummaryPrompt::create().format(input_variables)?;
+        let bpe = r50k_base()?;
+        let budget = max_tokens.saturating_sub(bpe.encode_ordinary(&template).len());
+        let (text, left_out) = Self::reduce_text(&group_summaries, budget)?;
+        if !left_out.is_empty() {
+            info!(
+                "group summaries over {} tokens, left out of the final summary: {:?}",
+                budget, left_out
+            );
+        }
+        let mut input_variables = HashMap::new();
+        input_variables.insert("text", text.as_str());
+        let prompt = GithubPRSummaryPrompt::create().format(input_variables)?;
+        let final_summary = llm.generate(&prompt).await?.generation;
+
+        let mut details = group_summaries
+            .iter()
+            .map(|(group, summary)| format!("#### `{}`\n{}", group, summary))
+            .collect::<Vec<_>>();
+        if !skipped_groups.is_empty() {
+            details.push(format!(
+                "Not summarized: {}",
+                skipped_groups
+                    .iter()
+                    .map(|x| format!("`{}`", x))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        Ok(format!(
+            "{}\n\n<details>\n<summary>Summary by directory</summary>\n\n{}\n</details>",
+            final_summary,
+            details.join("\n\n")
+        ))
+    }
+
+    /// The input of the final summary from the group summaries in order, within `max_tokens`.
+    /// The group overflowing is truncated if there is room for it, the rest are left out.
+    /// Returns the text and the groups left out.
+    pub fn reduce_text(
+        group_summaries: &[(String, String)],
+        max_tokens: usize,
+    ) -> Result<(String, Vec<String>)> {
+        let bpe = r50k_base()?;
+        let tokens = |text: &str| bpe.encode_ordinary(text).len();
+        let mut parts: Vec<String> = vec![];
+        let mut used = 0;
+        let mut left_out = vec![];
+        for (group, summary) in group_summaries {
+            if !left_out.is_empty() {
+                left_out.push(group.clone());
+                continue;
+            }
+            let header = format!("Changes in `{}`:\n", group);
+            let text = format!("{}{}", header, summary);
+            // The parts are joined by a newline.
+            let remaining = max_tokens.saturating_sub(used + 1);
+            let text_tokens = tokens(&text);
+            if text_tokens <= remaining {
+                used += text_tokens + 1;
+                parts.push(text);
+                continue;
+            }
+
+            let header_tokens = tokens(&header);
+            if remaining >= header_tokens + MIN_GROUP_TOKENS {
+                let encoded = bpe.encode_ordinary(summary);
+                // A token may end in the middle of a char, back off until it decodes.
+                let mut end = remaining - header_tokens;
+                while end > 0 && bpe.decode(encoded[..end].to_vec()).is_err() {
+                    end -= 1;
+                }
+                let head = bpe.decode(encoded[..end].to_vec()).unwrap_or_default();
+                parts.push(format!("{}{}", header, head));
+                // The later groups are left out.
+                used = max_tokens;
+                continue;
+            }
+            left_out.push(group.clone());
+        }
+        Ok((parts.join("\n"), left_out))
+    }
+
+    /// Group the file patches by the first `depth` directories of the file path,
+    /// larger groups first.
+    pub fn group_patches(patches: &Documents, depth: usize) -> Vec<(String, Vec<Document>)> {
+        let mut groups: BTreeMap<String, Vec<Document>> = BTreeMap::new();
+        for patch in patches {
+            let path = Self::patch_path(&patch.content);
+            let dirs = path.split('/').collect::<Vec<_>>();
+            let dirs = &dirs[..dirs.len() - 1];
+            let group = if dirs.is_empty() {
+                "/".to_string()
+            } else {
+                dirs[..dirs.len().min(depth.max(1))].join("/")
+            };
+            groups.entry(group).or_default().push(patch);
+        }
+
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(_, patches)| std::cmp::Reverse(patches.len()));
+        groups
+    }
+
+    /// Merge the patches into chunks of `chunk_size`, the patches beyond `max_tokens` are dropped.
+    /// Returns the chunks and the number of dropped patches.
+    fn chunk_patches(
+        patches: &[Document],
+        chunk_size: usize,
+        max_tokens: usize,
+    ) -> (Documents, usize) {
+        let path = patches.first().map(|x| x.path.clone()).unwrap_or_default();
+        let chunks = Documents::create();
+        let mut chunk = String::new();
+        let mut tokens = 0;
+        let mut dropped = 0;
+        for patch in patches {
+            let patch_tokens = patch.tokens();
+            if tokens + patch_tokens > max_tokens {
+                dropped += 1;
+                continue;
+            }
+            tokens += patch_tokens;
+
+            if !chunk.is_empty() && chunk.len() + patch.content.len() > chunk_size {
+                chunks.push(Document::create(&path, &chunk));
+                chunk.clear();
+            }
+            chunk.push('\n');
+            chunk.push_str(&patch.content);
+        }
+        if !chunk.is_empty() {
+            chunks.push(Document::create(&path, &chunk));
+        }
+        (chunks, dropped)
+    }
+
+    // The new path of the patch: `+++ b/src/lib.rs`
+    fn patch_path(patch: &str) -> String {
+        patch
+            .lines()
+            .find_map(|x| x.strip_prefix("+++ "))
+            .map(|x| x.split('\t').next().unwrap_or_default())
+            .map(|x| x.strip_prefix("b/").unwrap_or(x).to_string())
+            .unwrap_or_default()
+    }
+}
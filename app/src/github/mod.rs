@@ -12,6 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod github_client;
+mod github_command;
 mod github_comment;
+mod github_cursor;
+mod github_review;
+mod github_summary;
 
+pub use github_client::GithubClient;
+pub use github_client::GithubRepoClient;
+pub use github_command::GithubCommand;
 pub use github_comment::GithubComment;
+pub use github_cursor::ScanCursor;
+pub use github_review::DiffLines;
+pub use github_review::GithubReview;
+pub use github_review::ReviewComment;
+pub use github_review::ReviewSeverity;
+pub use github_summary::GithubSummary;
+pub use github_summary::SummaryComment;
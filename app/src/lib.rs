@@ -18,10 +18,71 @@ mod configs;
 mod github;
 mod qa;
 
+pub use api::qa_search_handler;
 pub use api::APIHandler;
+pub use base::escape_sql_array;
+pub use base::escape_sql_like;
 pub use base::escape_sql_string;
 pub use configs::Config;
+pub use configs::GithubConfig;
+pub use configs::GithubRepoConfig;
+pub use configs::QACollectionConfig;
+pub use configs::QAConfig;
+pub use configs::QALoaderConfig;
+pub use configs::DEFAULT_COLLECTION;
+pub use github::DiffLines;
+pub use github::GithubClient;
+pub use github::GithubCommand;
 pub use github::GithubComment;
+pub use github::GithubRepoClient;
+pub use github::GithubReview;
+pub use github::GithubSummary;
+pub use github::ReviewComment;
+pub use github::ReviewSeverity;
+pub use github::ScanCursor;
+pub use github::SummaryComment;
+pub use qa::QAAnswer;
+pub use qa::QACheckpoint;
+pub use qa::QAChunk;
+pub use qa::QAChunkStat;
+pub use qa::QACodeSplitter;
+pub use qa::QAContext;
+pub use qa::QAContextBuilder;
+pub use qa::QAContextOverflow;
+pub use qa::QACorpusStats;
 pub use qa::QADatabase;
+pub use qa::QADedupKeep;
+pub use qa::QADedupMode;
+pub use qa::QADeduplicator;
+pub use qa::QADuplicateGroup;
+pub use qa::QADuplicateStat;
 pub use qa::QAEmbedding;
+pub use qa::QAEval;
+pub use qa::QAEvalCase;
+pub use qa::QAEvalLLM;
+pub use qa::QAEvalReport;
+pub use qa::QAEvalResult;
+pub use qa::QAExplain;
+pub use qa::QAExplainParams;
+pub use qa::QAExplainTimings;
+pub use qa::QAExplainTokens;
+pub use qa::QAExtractiveLLM;
+pub use qa::QAFederatedSearch;
+pub use qa::QAFrontMatter;
+pub use qa::QAGitSource;
+pub use qa::QAIndexMeta;
+pub use qa::QALLMReranker;
+pub use qa::QALexicalReranker;
+pub use qa::QALoader;
+pub use qa::QALoaderKind;
+pub use qa::QAMarkdownSplitter;
+pub use qa::QAQueryRewriter;
+pub use qa::QAReranker;
+pub use qa::QARerankerKind;
+pub use qa::QASearch;
+pub use qa::QASearchFilter;
+pub use qa::QASearchHit;
+pub use qa::QASearchPage;
+pub use qa::QATextSplitter;
+pub use qa::QAVectorStore;
 pub use qa::QALLM;
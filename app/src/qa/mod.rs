@@ -12,10 +12,72 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod qa_checkpoint;
+mod qa_chunk;
+mod qa_code_splitter;
+mod qa_context;
+mod qa_corpus_stats;
 mod qa_db;
+mod qa_dedup;
 mod qa_embedding;
+mod qa_eval;
+mod qa_explain;
+mod qa_extractive_llm;
+mod qa_federated_search;
+mod qa_front_matter;
+mod qa_git_source;
+mod qa_index_meta;
 mod qa_llm;
+mod qa_loader;
+mod qa_markdown_splitter;
+mod qa_query_rewriter;
+mod qa_reranker;
+mod qa_search;
+mod qa_text_splitter;
+mod qa_vector_store;
 
+pub use qa_checkpoint::QACheckpoint;
+pub use qa_chunk::QAChunk;
+pub use qa_code_splitter::QACodeSplitter;
+pub use qa_context::QAContext;
+pub use qa_context::QAContextBuilder;
+pub use qa_context::QAContextOverflow;
+pub use qa_corpus_stats::QAChunkStat;
+pub use qa_corpus_stats::QACorpusStats;
+pub use qa_corpus_stats::QADuplicateStat;
 pub use qa_db::QADatabase;
+pub use qa_dedup::QADedupKeep;
+pub use qa_dedup::QADedupMode;
+pub use qa_dedup::QADeduplicator;
+pub use qa_dedup::QADuplicateGroup;
 pub use qa_embedding::QAEmbedding;
+pub use qa_eval::QAEval;
+pub use qa_eval::QAEvalCase;
+pub use qa_eval::QAEvalLLM;
+pub use qa_eval::QAEvalReport;
+pub use qa_eval::QAEvalResult;
+pub use qa_explain::QAAnswer;
+pub use qa_explain::QAExplain;
+pub use qa_explain::QAExplainParams;
+pub use qa_explain::QAExplainTimings;
+pub use qa_explain::QAExplainTokens;
+pub use qa_extractive_llm::QAExtractiveLLM;
+pub use qa_federated_search::QAFederatedSearch;
+pub use qa_front_matter::QAFrontMatter;
+pub use qa_git_source::QAGitSource;
+pub use qa_index_meta::QAIndexMeta;
 pub use qa_llm::QALLM;
+pub use qa_loader::QALoader;
+pub use qa_loader::QALoaderKind;
+pub use qa_markdown_splitter::QAMarkdownSplitter;
+pub use qa_query_rewriter::QAQueryRewriter;
+pub use qa_reranker::QALLMReranker;
+pub use qa_reranker::QALexicalReranker;
+pub use qa_reranker::QAReranker;
+pub use qa_reranker::QARerankerKind;
+pub use qa_search::QASearch;
+pub use qa_search::QASearchFilter;
+pub use qa_search::QASearchHit;
+pub use qa_search::QASearchPage;
+pub use qa_text_splitter::QATextSplitter;
+pub use qa_vector_store::QAVectorStore;
@@ -0,0 +1,99 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::qa::QAChunk;
+
+/// Batches embedded by an interrupted rebuild, saved as json to resume the same rebuild.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct QACheckpoint {
+    /// Fingerprint of the chunks and batches of the rebuild.
+    pub fingerprint: String,
+    /// Batches embedded.
+    pub done: BTreeSet<usize>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl QACheckpoint {
+    /// The checkpoint of the rebuild, empty if there is none or it's from another rebuild.
+    pub fn load(path: impl AsRef<Path>, fingerprint: &str) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut checkpoint = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<QACheckpoint>(&content) {
+                Ok(checkpoint) if checkpoint.fingerprint == fingerprint => checkpoint,
+                Ok(_) => QACheckpoint::default(),
+                Err(e) => {
+                    warn!("Ignore invalid checkpoint {}: {:?}", path.display(), e);
+                    QACheckpoint::default()
+                }
+            },
+            Err(_) => QACheckpoint::default(),
+        };
+        checkpoint.fingerprint = fingerprint.to_string();
+        checkpoint.path = path;
+        Ok(checkpoint)
+    }
+
+    /// The fingerprint of the chunks to embed into the table in batches of `batch_size`.
+    pub fn fingerprint(table: &str, batch_size: usize, chunks: &[QAChunk]) -> String {
+        let mut context = md5::Context::new();
+        context.consume(format!("{}\n{}\n", table, batch_size));
+        for chunk in chunks {
+            context.consume(format!("{}\n{}\n", chunk.path, chunk.content.len()));
+            context.consume(&chunk.content);
+        }
+        format!("{:x}", context.compute())
+    }
+
+    /// Whether the checkpoint resumes an interrupted rebuild.
+    pub fn is_resumed(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn is_done(&self, batch: usize) -> bool {
+        self.done.contains(&batch)
+    }
+
+    /// Mark the batch embedded and save the checkpoint.
+    pub fn mark_done(&mut self, batch: usize) -> Result<()> {
+        self.done.insert(batch);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename, an interrupted write never leaves a broken checkpoint.
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Remove the checkpoint once the rebuild is finished.
+    pub fn remove(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
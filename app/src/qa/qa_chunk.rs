@@ -0,0 +1,96 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Serialize;
+
+/// A chunk of a document to embed, with the metadata of its page.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QAChunk {
+    pub path: String,
+    pub content: String,
+    /// Title of the page from the front matter or the first heading.
+    pub title: String,
+    /// Slug of the page from the front matter.
+    pub slug: String,
+    /// Heading path of the chunk, eg: `Load Data > COPY INTO`.
+    pub headings: String,
+    /// Position of the chunk in its document, from 0.
+    pub index: usize,
+    /// Position of the parent heading section in the document, shared by the chunks of the section.
+    /// The whole document is the section of the chunks not split by headings.
+    pub section_id: usize,
+    /// Top directory of the document under `qa.docs_root`, eg: `12-load-data`.
+    pub doc_section: String,
+    /// Path of the document relative to `qa.docs_root`, or to the source root if it's not under it.
+    pub doc_path: String,
+    /// Product version of the page, from the front matter or `qa.docs_version`.
+    pub version: String,
+    /// Tags of the page from the front matter.
+    pub tags: Vec<String>,
+    /// Similarity to the query, only set by the search.
+    pub similarity: f32,
+    /// Collection of the chunk, only set by the federated search.
+    pub collection: String,
+    /// Weighted and normalized similarity, only set by the federated search.
+    pub score: f32,
+    /// Relevance to the query, only set by the reranker.
+    pub rerank_score: f32,
+}
+
+impl QAChunk {
+    pub fn create(path: &str, content: &str) -> Self {
+        QAChunk {
+            path: path.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the document path and section relative to the `docs_root` directory of the source root.
+    pub fn locate(&mut self, root: &str, docs_root: &str) {
+        let relative = Self::strip_dir(&self.path, root);
+        let docs_root = docs_root.trim_matches('/');
+        let relative = if docs_root.is_empty() {
+            relative
+        } else {
+            Self::strip_dir(relative, docs_root)
+        };
+        self.doc_section = match relative.split_once('/') {
+            Some((section, _)) => section.to_string(),
+            None => "".to_string(),
+        };
+        self.doc_path = relative.to_string();
+    }
+
+    /// The path under the directory, the path itself if it's not under the directory.
+    fn strip_dir<'a>(path: &'a str, dir: &str) -> &'a str {
+        match path.strip_prefix(dir.trim_end_matches('/')) {
+            Some(rest) if rest.starts_with('/') => rest,
+            _ => path,
+        }
+        .trim_start_matches('/')
+    }
+
+    /// The page url `<base_url><slug>` if both are set, the document path otherwise.
+    pub fn source(&self, base_url: &str) -> String {
+        if base_url.is_empty() || self.slug.is_empty() {
+            return self.path.clone();
+        }
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            self.slug.trim_start_matches('/')
+        )
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use llmchain::Document;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+use regex::Regex;
+
+/// Splitter of the source code, the chunks break at the function or type definitions.
+/// SQL files break at the end of the statements.
+pub struct QACodeSplitter {
+    pub splitter_chunk_size: usize,
+    definition: Regex,
+}
+
+impl QACodeSplitter {
+    pub fn create() -> Self {
+        let definition = Regex::new(&format!(
+            r"^\s{{0,4}}(pub(\([^)]*\))?\s+)?(export\s+)?(default\s+)?(async\s+)?(unsafe\s+)?({})\b",
+            Self::DEFINITIONS.join("|")
+        ))
+        .unwrap();
+        QACodeSplitter {
+            splitter_chunk_size: 1000,
+            definition,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.splitter_chunk_size = chunk_size;
+        self
+    }
+
+    /// Keywords starting a function or type definition.
+    const DEFINITIONS: [&'static str; 11] = [
+        "fn",
+        "def",
+        "func",
+        "function",
+        "class",
+        "impl",
+        "trait",
+        "struct",
+        "enum",
+        "interface",
+        "mod",
+    ];
+
+    fn split_text(&self, path: &str, text: &str) -> Vec<String> {
+        let is_sql = path.to_lowercase().ends_with(".sql");
+
+        // Blocks of one definition or statement, with its leading comments and attributes.
+        let mut blocks: Vec<Vec<&str>> = vec![];
+        let mut current: Vec<&str> = vec![];
+        let mut statement_end = false;
+        for line in text.lines() {
+            let starts = if is_sql {
+                statement_end && !line.trim().is_empty()
+            } else {
+                self.definition.is_match(line)
+            };
+            if starts {
+                let mut head = vec![];
+                while let Some(last) = current.last() {
+                    if !Self::is_comment(last) {
+                        break;
+                    }
+                    head.insert(0, *last);
+                    current.pop();
+                }
+                blocks.push(std::mem::take(&mut current));
+                current = head;
+            }
+            current.push(line);
+            if !line.trim().is_empty() {
+                statement_end = line.trim_end().ends_with(';');
+            }
+        }
+        blocks.push(current);
+
+        // Merge the small blocks, split the large ones by lines.
+        let mut chunks = vec![];
+        let mut chunk = String::new();
+        for block in blocks {
+            let block = block.join("\n");
+            if block.trim().is_empty() {
+                continue;
+            }
+            if !chunk.is_empty() && chunk.len() + block.len() >= self.splitter_chunk_size {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            if block.len() > self.splitter_chunk_size {
+                chunks.extend(self.split_lines(&block));
+                continue;
+            }
+            if !chunk.is_empty() {
+                chunk.push('\n');
+            }
+            chunk.push_str(&block);
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    fn split_lines(&self, block: &str) -> Vec<String> {
+        let mut chunks = vec![];
+        let mut chunk = String::new();
+        for line in block.lines() {
+            if !chunk.is_empty() && chunk.len() + line.len() >= self.splitter_chunk_size {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            if !chunk.is_empty() {
+                chunk.push('\n');
+            }
+            chunk.push_str(line);
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk);
+        }
+        chunks
+    }
+
+    fn is_comment(line: &str) -> bool {
+        let line = line.trim_start();
+        ["//", "/*", "*", "#", "--", "@"]
+            .iter()
+            .any(|x| line.starts_with(x))
+    }
+}
+
+impl DocumentSplitter for QACodeSplitter {
+    fn separators(&self) -> Vec<String> {
+        Self::DEFINITIONS.iter().map(|x| x.to_string()).collect()
+    }
+
+    fn split_documents(&self, documents: &Documents) -> Result<Documents> {
+        let result = Documents::create();
+        for document in documents {
+            for chunk in self.split_text(&document.path, &document.content) {
+                result.push(Document::create(&document.path, &chunk));
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::LLM;
+use log::warn;
+use tiktoken_rs::r50k_base;
+use tiktoken_rs::CoreBPE;
+
+use crate::qa::QAChunk;
+
+/// Min tokens left for the content of a shortened chunk, the chunk is dropped otherwise.
+const MIN_CONTENT_TOKENS: usize = 32;
+
+/// How to shorten the chunk overflowing the context budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QAContextOverflow {
+    /// Keep the head of the chunk.
+    Truncate,
+    /// Ask the LLM to summarize the chunk, truncated if the summary still overflows.
+    Summarize,
+}
+
+impl FromStr for QAContextOverflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "truncate" | "" => Ok(QAContextOverflow::Truncate),
+            "summarize" => Ok(QAContextOverflow::Summarize),
+            _ => Err(anyhow!(
+                "unknown context overflow: {}, expect truncate|summarize",
+                s
+            )),
+        }
+    }
+}
+
+/// The contexts of the prompt within the budget, best ranked first.
+#[derive(Debug, Clone, Default)]
+pub struct QAContext {
+    pub chunks: Vec<QAChunk>,
+    /// The formatted context of each chunk.
+    pub contexts: Vec<String>,
+    /// Tokens of all the contexts.
+    pub tokens: usize,
+    /// Chunks truncated or summarized to fit the budget.
+    pub shortened: usize,
+    /// Chunks dropped for the budget.
+    pub dropped: usize,
+    /// Chunks of the same documents merged into the chunks.
+    pub expanded: usize,
+    /// Positions in the document of the chunks merged into each chunk.
+    pub positions: Vec<Vec<usize>>,
+}
+
+impl QAContext {
+    /// All the contexts of the prompt.
+    pub fn text(&self) -> String {
+        self.contexts.join("\n")
+    }
+
+    fn push(&mut self, chunk: QAChunk, context: String, tokens: usize) {
+        self.positions.push(vec![chunk.index]);
+        self.chunks.push(chunk);
+        self.contexts.push(context);
+        self.tokens += tokens;
+    }
+}
+
+/// Assembles the ranked chunks into the contexts of the prompt within a token budget.
+pub struct QAContextBuilder {
+    llm: Arc<dyn LLM>,
+    bpe: CoreBPE,
+    max_tokens: usize,
+    overflow: QAContextOverflow,
+}
+
+impl QAContextBuilder {
+    pub fn create(llm: Arc<dyn LLM>, max_tokens: usize) -> Result<Self> {
+        Ok(QAContextBuilder {
+            llm,
+            bpe: r50k_base()?,
+            max_tokens,
+            overflow: QAContextOverflow::Truncate,
+        })
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: QAContextOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub fn tokens(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+
+    /// Add the chunks in rank order while they fit the budget.
+    /// The first chunk overflowing is shortened if there is room for it, the rest are dropped.
+    pub async fn build<F>(&self, chunks: Vec<QAChunk>, format: F) -> Result<QAContext>
+    where F: Fn(&QAChunk) -> String + Send + Sync {
+        let mut context = QAContext::default();
+        let total = chunks.len();
+        for mut chunk in chunks {
+            let remaining = self.max_tokens.saturating_sub(context.tokens);
+            let text = format(&chunk);
+            let tokens = self.tokens(&text);
+            if tokens <= remaining {
+                context.push(chunk, text, tokens);
+                continue;
+            }
+
+            // The formatted text may take fewer tokens than the content alone.
+            let overhead = tokens.saturating_sub(self.tokens(&chunk.content));
+            if remaining < overhead + MIN_CONTENT_TOKENS {
+                break;
+            }
+            let budget = remaining - overhead;
+            let content = match self.overflow {
+                QAContextOverflow::Truncate => chunk.content.clone(),
+                QAContextOverflow::Summarize => self.summarize(&chunk.content, budget).await,
+            };
+            chunk.content = self.truncate(&content, budget);
+            let text = format(&chunk);
+            let tokens = self.tokens(&text);
+            if tokens > remaining {
+                break;
+            }
+            context.push(chunk, text, tokens);
+            context.shortened += 1;
+        }
+        context.dropped = total - context.chunks.len();
+        Ok(context)
+    }
+
+    /// Replace the chunks with their whole parent section while the budget allows, best ranked first.
+    /// The lower ranked chunks of a section merged are removed.
+    pub fn expand_sections<F>(&self, context: &mut QAContext, sections: &[QAChunk], format: F)
+    where F: Fn(&QAChunk) -> String {
+        let mut i = 0;
+        while i < context.chunks.len() {
+            let hit = context.chunks[i].clone();
+            let same_section = |x: &QAChunk| {
+                x.collection == hit.collection
+                    && x.path == hit.path
+                    && x.section_id == hit.section_id
+            };
+            let mut section = sections
+                .iter()
+                .filter(|x| same_section(x))
+                .collect::<Vec<_>>();
+            section.sort_by_key(|x| x.index);
+            let indexes = section.iter().map(|x| x.index).collect::<Vec<_>>();
+
+            let merged_before = (0..i).any(|j| {
+                same_section(&context.chunks[j])
+                    && context.positions[j].iter().any(|x| indexes.contains(x))
+            });
+            if merged_before || section.len() <= context.positions[i].len() {
+                i += 1;
+                continue;
+            }
+
+            let later = (i + 1..context.chunks.len())
+                .filter(|j| same_section(&context.chunks[*j]))
+                .collect::<Vec<_>>();
+            let chunk = QAChunk {
+                content: section
+                    .iter()
+                    .map(|x| x.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                ..hit
+            };
+            let text = format(&chunk);
+            let tokens = self.tokens(&text);
+            let freed = std::iter::once(i)
+                .chain(later.iter().copied())
+                .map(|j| self.tokens(&context.contexts[j]))
+                .sum::<usize>();
+            if context.tokens - freed + tokens > self.max_tokens {
+                i += 1;
+                continue;
+            }
+
+            context.tokens = context.tokens - freed + tokens;
+            context.contexts[i] = text;
+            context.chunks[i].content = chunk.content;
+            context.expanded += section.len() - 1 - later.len();
+            context.positions[i] = indexes;
+            for j in later.into_iter().rev() {
+                context.chunks.remove(j);
+                context.contexts.remove(j);
+                context.positions.remove(j);
+            }
+            i += 1;
+        }
+    }
+
+    /// Merge the adjacent chunks of the same document into the chunks while the budget allows.
+    /// The best ranked chunks are expanded first, with their closest neighbors first.
+    pub fn expand<F>(&self, context: &mut QAContext, neighbors: &[QAChunk], format: F)
+    where F: Fn(&QAChunk) -> String {
+        for i in 0..context.chunks.len() {
+            let hit = context.chunks[i].clone();
+            let mut candidates = neighbors
+                .iter()
+                .filter(|x| x.collection == hit.collection && x.path == hit.path)
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|x| (x.index.abs_diff(hit.index), x.index));
+
+            for neighbor in candidates {
+                // A neighbor is merged into one chunk only.
+                let taken =
+                    context
+                        .chunks
+                        .iter()
+                        .zip(context.positions.iter())
+                        .any(|(x, positions)| {
+                            x.collection == neighbor.collection
+                                && x.path == neighbor.path
+                                && positions.contains(&neighbor.index)
+                        });
+                let first = context.positions[i]
+                    .iter()
+                    .min()
+                    .copied()
+                    .unwrap_or_default();
+                let last = context.positions[i]
+                    .iter()
+                    .max()
+                    .copied()
+                    .unwrap_or_default();
+                // No gap between the merged chunks.
+                let before = neighbor.index + 1 == first;
+                let after = neighbor.index == last + 1;
+                if taken || !(before || after) {
+                    continue;
+                }
+
+                let current = &context.chunks[i].content;
+                let content = if before {
+                    format!("{}\n{}", neighbor.content, current)
+                } else {
+                    format!("{}\n{}", current, neighbor.content)
+                };
+                let chunk = QAChunk {
+                    content,
+                    ..hit.clone()
+                };
+                let text = format(&chunk);
+                let tokens = self.tokens(&text);
+                let current = self.tokens(&context.contexts[i]);
+                if context.tokens - current + tokens > self.max_tokens {
+                    continue;
+                }
+
+                context.tokens = context.tokens - current + tokens;
+                context.contexts[i] = text;
+                context.chunks[i].content = chunk.content;
+                context.expanded += 1;
+                context.positions[i].push(neighbor.index);
+            }
+        }
+    }
+
+    async fn summarize(&self, content: &str, tokens: usize) -> String {
+        let prompt = format!(
+            "Summarize the documentation below in at most {} words. \
+             Keep the SQL statements, function names and options unchanged, reply with the summary only.\n\n{}",
+            tokens * 3 / 4,
+            content
+        );
+        match self.llm.generate(&prompt).await {
+            Ok(result) => result.generation.trim().to_string(),
+            Err(e) => {
+                warn!("Failed to summarize the context, truncate it: {:?}", e);
+                content.to_string()
+            }
+        }
+    }
+
+    /// The head of the text within the tokens.
+    fn truncate(&self, text: &str, tokens: usize) -> String {
+        let encoded = self.bpe.encode_ordinary(text);
+        if encoded.len() <= tokens {
+            return text.to_string();
+        }
+        // A token may end in the middle of a char, back off until it decodes.
+        let mut end = tokens;
+        while end > 0 {
+            if let Ok(head) = self.bpe.decode(encoded[..end].to_vec()) {
+                return head;
+            }
+            end -= 1;
+        }
+        String::new()
+    }
+}
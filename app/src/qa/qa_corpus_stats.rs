@@ -0,0 +1,220 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::Result;
+use llmchain::Documents;
+use serde::Serialize;
+use tiktoken_rs::r50k_base;
+
+use crate::qa::QAChunk;
+use crate::qa::QADuplicateGroup;
+
+/// Price of the embedding model, text-embedding-ada-002.
+const EMBEDDING_PRICE_PER_1K_TOKENS: f64 = 0.0001;
+
+/// Chunks listed in the largest, smallest and duplicate sections of the report.
+const REPORT_TOP: usize = 5;
+
+/// A chunk listed in the report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QAChunkStat {
+    pub path: String,
+    pub headings: String,
+    pub tokens: usize,
+}
+
+impl QAChunkStat {
+    /// The path of the chunk with its heading path if any.
+    pub fn location(&self) -> String {
+        if self.headings.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{} [{}]", self.path, self.headings)
+        }
+    }
+}
+
+/// Chunks with the same content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QADuplicateStat {
+    pub paths: Vec<String>,
+    pub preview: String,
+}
+
+/// Statistics of the corpus to embed, reported by `--rebuild --dry-run`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QACorpusStats {
+    pub files: usize,
+    pub skipped_files: Vec<String>,
+    pub chunks: usize,
+    pub tokens: usize,
+    /// Estimated cost in USD of embedding all the chunks.
+    pub embedding_cost: f64,
+    pub largest_chunks: Vec<QAChunkStat>,
+    pub smallest_chunks: Vec<QAChunkStat>,
+    /// Chunks with the same content as an earlier chunk.
+    pub duplicate_chunks: usize,
+    pub duplicates: Vec<QADuplicateStat>,
+    /// Chunks removed by the deduplication.
+    pub merged_chunks: usize,
+    pub merged: Vec<QADuplicateStat>,
+}
+
+impl QACorpusStats {
+    pub fn create(
+        documents: &Documents,
+        chunks: &[QAChunk],
+        skipped_files: &[String],
+    ) -> Result<Self> {
+        let bpe = r50k_base()?;
+        let mut stats = chunks
+            .iter()
+            .map(|x| QAChunkStat {
+                path: x.path.clone(),
+                headings: x.headings.clone(),
+                tokens: bpe.encode_ordinary(&x.content).len(),
+            })
+            .collect::<Vec<_>>();
+        let tokens = stats.iter().map(|x| x.tokens).sum::<usize>();
+
+        stats.sort_by_key(|x| Reverse(x.tokens));
+        let largest_chunks = stats.iter().take(REPORT_TOP).cloned().collect();
+        let smallest_chunks = stats.iter().rev().take(REPORT_TOP).cloned().collect();
+
+        Ok(QACorpusStats {
+            files: documents.len(),
+            skipped_files: skipped_files.to_vec(),
+            chunks: chunks.len(),
+            tokens,
+            embedding_cost: tokens as f64 / 1000.0 * EMBEDDING_PRICE_PER_1K_TOKENS,
+            largest_chunks,
+            smallest_chunks,
+            ..Default::default()
+        }
+        .with_duplicates(chunks))
+    }
+
+    /// The stats with the duplicate chunks of the given chunks, eg: the chunks before the deduplication.
+    pub fn with_duplicates(mut self, chunks: &[QAChunk]) -> Self {
+        // Group the chunks by content, in the order of their first chunk.
+        let mut groups: Vec<Vec<&QAChunk>> = vec![];
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for chunk in chunks {
+            match index.get(chunk.content.trim()) {
+                Some(i) => groups[*i].push(chunk),
+                None => {
+                    index.insert(chunk.content.trim(), groups.len());
+                    groups.push(vec![chunk]);
+                }
+            }
+        }
+        let mut groups = groups
+            .into_iter()
+            .filter(|x| x.len() > 1)
+            .collect::<Vec<_>>();
+        self.duplicate_chunks = groups.iter().map(|x| x.len() - 1).sum();
+        groups.sort_by_key(|x| Reverse(x.len()));
+        self.duplicates = groups
+            .iter()
+            .take(REPORT_TOP)
+            .map(|x| QADuplicateStat {
+                paths: x.iter().map(|c| c.path.clone()).collect(),
+                preview: Self::preview(&x[0].content),
+            })
+            .collect();
+        self
+    }
+
+    /// The stats with the chunks removed by the deduplication, the kept path first.
+    pub fn with_merged(mut self, groups: &[QADuplicateGroup]) -> Self {
+        self.merged_chunks = groups.iter().map(|x| x.removed.len()).sum();
+        let mut groups = groups.iter().collect::<Vec<_>>();
+        groups.sort_by_key(|x| Reverse(x.removed.len()));
+        self.merged = groups
+            .iter()
+            .take(REPORT_TOP)
+            .map(|x| QADuplicateStat {
+                paths: std::iter::once(&x.kept)
+                    .chain(x.removed.iter())
+                    .map(|c| c.path.clone())
+                    .collect(),
+                preview: Self::preview(&x.kept.content),
+            })
+            .collect();
+        self
+    }
+
+    /// The human readable report.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(report, "files: {}", self.files);
+        let _ = writeln!(report, "files skipped: {}", self.skipped_files.len());
+        let _ = writeln!(report, "chunks: {}", self.chunks);
+        let _ = writeln!(report, "tokens: {}", self.tokens);
+        let _ = writeln!(
+            report,
+            "estimated embedding cost: ${:.4} (${} / 1K tokens)",
+            self.embedding_cost, EMBEDDING_PRICE_PER_1K_TOKENS
+        );
+        let _ = writeln!(report, "duplicate chunks: {}", self.duplicate_chunks);
+        let _ = writeln!(report, "chunks merged by dedup: {}", self.merged_chunks);
+
+        let _ = writeln!(report, "\nlargest chunks:");
+        for x in &self.largest_chunks {
+            let _ = writeln!(report, "  {} tokens  {}", x.tokens, x.location());
+        }
+        let _ = writeln!(report, "\nsmallest chunks:");
+        for x in &self.smallest_chunks {
+            let _ = writeln!(report, "  {} tokens  {}", x.tokens, x.location());
+        }
+        if !self.duplicates.is_empty() {
+            let _ = writeln!(report, "\nduplicates:");
+            for x in &self.duplicates {
+                let _ = writeln!(report, "  {} x {:?}", x.paths.len(), x.preview);
+                for path in &x.paths {
+                    let _ = writeln!(report, "    {}", path);
+                }
+            }
+        }
+        if !self.merged.is_empty() {
+            let _ = writeln!(report, "\nmerged by dedup, the first path is kept:");
+            for x in &self.merged {
+                let _ = writeln!(report, "  {} x {:?}", x.paths.len(), x.preview);
+                for path in &x.paths {
+                    let _ = writeln!(report, "    {}", path);
+                }
+            }
+        }
+        if !self.skipped_files.is_empty() {
+            let _ = writeln!(report, "\nfiles skipped:");
+            for path in &self.skipped_files {
+                let _ = writeln!(report, "  {}", path);
+            }
+        }
+        report
+    }
+
+    /// The first line of the content, at most 60 chars.
+    fn preview(content: &str) -> String {
+        let line = content.trim().lines().next().unwrap_or_default();
+        match line.char_indices().nth(60) {
+            Some((i, _)) => format!("{}...", &line[..i]),
+            None => line.to_string(),
+        }
+    }
+}
@@ -12,13 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::anyhow;
 use anyhow::Result;
 use chrono::DateTime;
 use chrono::Utc;
 use databend_driver::Client;
 use databend_driver::Connection;
 
+use crate::base::escape_sql_array;
 use crate::base::escape_sql_string;
+use crate::qa::QAIndexMeta;
 use crate::Config;
 
 #[derive(Clone)]
@@ -41,6 +44,107 @@ impl QADatabase {
         })
     }
 
+    /// The table of the index build metadata.
+    pub fn meta_table(&self) -> String {
+        format!("{}_meta", self.table)
+    }
+
+    pub async fn init_index_meta(&self) -> Result<()> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {}.{} (meta VARCHAR, ts TIMESTAMP)",
+            self.database,
+            self.meta_table()
+        );
+        let _ = self.conn.exec(&sql).await?;
+        Ok(())
+    }
+
+    /// Metadata of the last index build, None if the index is never built.
+    pub async fn latest_index_meta(&self) -> Result<Option<QAIndexMeta>> {
+        let sql = format!(
+            "SELECT meta FROM {}.{} ORDER BY ts DESC LIMIT 1",
+            self.database,
+            self.meta_table()
+        );
+        let row = match self.conn.query_row(&sql).await? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let (meta,): (String,) = row.try_into().map_err(|e: String| anyhow!(e))?;
+        Ok(Some(serde_json::from_str(&meta)?))
+    }
+
+    pub async fn insert_index_meta(&self, meta: &QAIndexMeta) -> Result<()> {
+        let now: DateTime<Utc> = Utc::now();
+        let sql = format!(
+            "INSERT INTO {}.{} (meta, ts) VALUES ('{}', '{}')",
+            self.database,
+            self.meta_table(),
+            escape_sql_string(&serde_json::to_string(meta)?),
+            now.format("%Y-%m-%d %H:%M:%S%.6f"),
+        );
+        let _ = self.conn.exec(&sql).await?;
+        Ok(())
+    }
+
+    /// Delete all the embedded chunks, the table holds the chunks of one source.
+    pub async fn delete_all_documents(&self) -> Result<()> {
+        let sql = format!("DELETE FROM {}.{}", self.database, self.table);
+        let _ = self.conn.exec(&sql).await?;
+        Ok(())
+    }
+
+    /// Delete the embedded chunks of the documents.
+    pub async fn delete_documents(&self, paths: &[String]) -> Result<()> {
+        for paths in paths.chunks(100) {
+            let paths = paths
+                .iter()
+                .map(|x| format!("'{}'", escape_sql_string(x)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let sql = format!(
+                "DELETE FROM {}.{} WHERE path IN ({})",
+                self.database, self.table, paths
+            );
+            let _ = self.conn.exec(&sql).await?;
+        }
+        Ok(())
+    }
+
+    /// Create the answer table if it's set, the tables created before get the new columns.
+    pub async fn init_answer_table(&self) -> Result<()> {
+        if self.answer_table.is_empty() {
+            return Ok(());
+        }
+
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {}.{} (question VARCHAR, prompt VARCHAR, similar_distances ARRAY(FLOAT32), \
+            similar_sections VARCHAR, answer VARCHAR, rewrites ARRAY(VARCHAR), ts TIMESTAMP)",
+            self.database, self.answer_table
+        );
+        let _ = self.conn.exec(&sql).await?;
+
+        let sql = format!(
+            "SELECT count(*) FROM system.columns WHERE database = '{}' AND table = '{}' AND name = 'rewrites'",
+            escape_sql_string(&self.database),
+            escape_sql_string(&self.answer_table)
+        );
+        let row = self.conn.query_row(&sql).await?;
+        let (count,): (u64,) = match row {
+            Some(row) => row.try_into().map_err(|e: String| anyhow!(e))?,
+            None => (0,),
+        };
+        if count == 0 {
+            let sql = format!(
+                "ALTER TABLE {}.{} ADD COLUMN rewrites ARRAY(VARCHAR)",
+                self.database, self.answer_table
+            );
+            let _ = self.conn.exec(&sql).await?;
+        }
+        Ok(())
+    }
+
+    /// Record the answer with the rewrites of the question.
     pub async fn insert_answer(
         &self,
         query: &str,
@@ -48,6 +152,7 @@ impl QADatabase {
         similar_distances: &[f32],
         similar_sections: &str,
         answer: &str,
+        rewrites: &[String],
     ) -> Result<()> {
         if self.answer_table.is_empty() {
             return Ok(());
@@ -56,7 +161,7 @@ impl QADatabase {
         let now: DateTime<Utc> = Utc::now();
         let now_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
         let sql = format!(
-            "INSERT INTO {}.{} (question, prompt, similar_distances, similar_sections, answer, ts) VALUES ('{}','{}', {:?}, '{}', '{}', '{}')",
+            "INSERT INTO {}.{} (question, prompt, similar_distances, similar_sections, answer, rewrites, ts) VALUES ('{}','{}', {:?}, '{}', '{}', {}, '{}')",
             self.database,
             self.answer_table,
             escape_sql_string(query),
@@ -64,6 +169,7 @@ impl QADatabase {
             similar_distances,
             escape_sql_string(similar_sections),
             escape_sql_string(answer),
+            escape_sql_array(rewrites),
             now_str,
         );
         let _ = self.conn.exec(&sql).await?;
@@ -0,0 +1,274 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use glob::Pattern;
+
+use crate::configs::QAConfig;
+use crate::qa::QAChunk;
+
+/// Words of a shingle.
+const SHINGLE_SIZE: usize = 3;
+/// MinHash signature of a chunk is `BANDS * ROWS` hashes, the chunks sharing a band are
+/// the near duplicate candidates.
+const BANDS: usize = 16;
+const ROWS: usize = 4;
+
+/// How the duplicate chunks are detected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QADedupMode {
+    Off,
+    /// Same content after normalizing the case, punctuation and whitespace.
+    Exact,
+    /// Exact duplicates and the chunks whose word shingles are similar enough.
+    Near,
+}
+
+impl FromStr for QADedupMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(QADedupMode::Off),
+            "exact" => Ok(QADedupMode::Exact),
+            "near" => Ok(QADedupMode::Near),
+            _ => Err(anyhow!("unknown dedup mode: {}, expect off|exact|near", s)),
+        }
+    }
+}
+
+/// Which chunk of the duplicates is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QADedupKeep {
+    /// The first one in the load order.
+    First,
+    /// The one with the shortest path, eg: `docs/sql.md` over `docs/v1.0/sql.md`.
+    ShortestPath,
+}
+
+impl FromStr for QADedupKeep {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "first" => Ok(QADedupKeep::First),
+            "shortest_path" => Ok(QADedupKeep::ShortestPath),
+            _ => Err(anyhow!(
+                "unknown dedup keep rule: {}, expect first|shortest_path",
+                s
+            )),
+        }
+    }
+}
+
+/// Chunks merged into the kept one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QADuplicateGroup {
+    pub kept: QAChunk,
+    pub removed: Vec<QAChunk>,
+}
+
+/// Removes the duplicate chunks before embedding.
+pub struct QADeduplicator {
+    mode: QADedupMode,
+    similarity: f64,
+    keep: QADedupKeep,
+    prefer_paths: Vec<Pattern>,
+}
+
+struct Signature {
+    words: String,
+    shingles: HashSet<u64>,
+    minhash: Vec<u64>,
+}
+
+impl QADeduplicator {
+    pub fn create(conf: &QAConfig) -> Result<Self> {
+        if conf.dedup_similarity == 0 || conf.dedup_similarity > 100 {
+            return Err(anyhow!(
+                "dedup_similarity {} must be in 1..=100",
+                conf.dedup_similarity
+            ));
+        }
+        let prefer_paths = conf
+            .dedup_prefer_paths
+            .iter()
+            .map(|x| Pattern::new(x))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(QADeduplicator {
+            mode: QADedupMode::from_str(&conf.dedup)?,
+            similarity: conf.dedup_similarity as f64 / 100.0,
+            keep: QADedupKeep::from_str(&conf.dedup_keep)?,
+            prefer_paths,
+        })
+    }
+
+    /// Remove the duplicate chunks, return the kept chunks in their order and the groups merged.
+    pub fn dedup(&self, chunks: Vec<QAChunk>) -> (Vec<QAChunk>, Vec<QADuplicateGroup>) {
+        if self.mode == QADedupMode::Off {
+            return (chunks, vec![]);
+        }
+
+        // Visit the chunks to keep first, the later ones are merged into them.
+        let mut order = (0..chunks.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| {
+            let chunk = &chunks[*i];
+            let preferred = self.prefer_paths.iter().any(|x| x.matches(&chunk.path));
+            let path_len = match self.keep {
+                QADedupKeep::First => 0,
+                QADedupKeep::ShortestPath => chunk.path.len(),
+            };
+            (!preferred, path_len, *i)
+        });
+
+        let signatures = chunks
+            .iter()
+            .map(|x| self.signature(&x.content))
+            .collect::<Vec<_>>();
+        let mut exact: HashMap<&str, usize> = HashMap::new();
+        let mut bands: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+        // Kept chunk of each chunk.
+        let mut kept_by: Vec<Option<usize>> = vec![None; chunks.len()];
+
+        for i in order {
+            let signature = &signatures[i];
+            let duplicate = match exact.get(signature.words.as_str()) {
+                Some(kept) => Some(*kept),
+                None if self.mode == QADedupMode::Near => {
+                    self.near_duplicate(&signatures, &bands, i)
+                }
+                None => None,
+            };
+
+            match duplicate {
+                Some(kept) => kept_by[i] = Some(kept),
+                None => {
+                    exact.insert(&signature.words, i);
+                    for (band, key) in Self::band_keys(&signature.minhash) {
+                        bands.entry((band, key)).or_default().push(i);
+                    }
+                }
+            }
+        }
+
+        let mut groups: Vec<QADuplicateGroup> = vec![];
+        let mut group_index: HashMap<usize, usize> = HashMap::new();
+        for (i, kept) in kept_by.iter().enumerate() {
+            if let Some(kept) = kept {
+                let index = *group_index.entry(*kept).or_insert_with(|| {
+                    groups.push(QADuplicateGroup {
+                        kept: chunks[*kept].clone(),
+                        removed: vec![],
+                    });
+                    groups.len() - 1
+                });
+                groups[index].removed.push(chunks[i].clone());
+            }
+        }
+
+        let chunks = chunks
+            .into_iter()
+            .zip(kept_by)
+            .filter(|(_, kept)| kept.is_none())
+            .map(|(chunk, _)| chunk)
+            .collect();
+        (chunks, groups)
+    }
+
+    /// The kept chunk sharing a band with the chunk and similar enough to it.
+    fn near_duplicate(
+        &self,
+        signatures: &[Signature],
+        bands: &HashMap<(usize, u64), Vec<usize>>,
+        i: usize,
+    ) -> Option<usize> {
+        let signature = &signatures[i];
+        let mut seen = HashSet::new();
+        for key in Self::band_keys(&signature.minhash) {
+            for candidate in bands.get(&key).into_iter().flatten() {
+                if seen.insert(*candidate)
+                    && Self::jaccard(&signature.shingles, &signatures[*candidate].shingles)
+                        >= self.similarity
+                {
+                    return Some(*candidate);
+                }
+            }
+        }
+        None
+    }
+
+    fn signature(&self, content: &str) -> Signature {
+        let normalized = content
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+            .collect::<String>();
+        let words = normalized.split_whitespace().collect::<Vec<_>>();
+
+        let shingles = words
+            .windows(SHINGLE_SIZE.min(words.len().max(1)))
+            .map(|x| Self::hash(&x))
+            .collect::<HashSet<_>>();
+        let minhash = if self.mode == QADedupMode::Near {
+            (0..BANDS * ROWS)
+                .map(|seed| {
+                    shingles
+                        .iter()
+                        .map(|x| Self::hash(&(seed, x)))
+                        .min()
+                        .unwrap_or_default()
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Signature {
+            words: words.join(" "),
+            shingles,
+            minhash,
+        }
+    }
+
+    fn band_keys(minhash: &[u64]) -> Vec<(usize, u64)> {
+        minhash
+            .chunks(ROWS)
+            .enumerate()
+            .map(|(band, rows)| (band, Self::hash(&rows)))
+            .collect()
+    }
+
+    fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+        if a.is_empty() && b.is_empty() {
+            return 1.0;
+        }
+        let intersection = a.intersection(b).count();
+        intersection as f64 / (a.len() + b.len() - intersection) as f64
+    }
+
+    fn hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
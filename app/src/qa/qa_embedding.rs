@@ -12,65 +12,348 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 use std::time::Instant;
 
 use anyhow::Result;
-use llmchain::DatabendEmbedding;
-use llmchain::DatabendVectorStore;
-use llmchain::DocumentLoader;
-use llmchain::DocumentPath;
-use llmchain::DocumentSplitter;
-use llmchain::MarkdownLoader;
-use llmchain::MarkdownSplitter;
-use llmchain::VectorStore;
+use futures::stream;
+use futures::StreamExt;
+use llmchain::Documents;
 use log::info;
+use log::warn;
+use tokio::time::sleep;
 
+use crate::configs::QAConfig;
+use crate::qa::QACheckpoint;
+use crate::qa::QAChunk;
+use crate::qa::QACorpusStats;
+use crate::qa::QADeduplicator;
+use crate::qa::QADuplicateGroup;
+use crate::qa::QAGitSource;
+use crate::qa::QAIndexMeta;
+use crate::qa::QALoader;
+use crate::qa::QAVectorStore;
 use crate::Config;
+use crate::QADatabase;
 
 pub struct QAEmbedding {
     conf: Config,
 }
 
+/// Files loaded from the source.
+struct LoadedFiles {
+    documents: Documents,
+    /// Directory of the documents, the metadata paths are relative to it.
+    root: String,
+    meta: QAIndexMeta,
+    stale: StaleChunks,
+    /// Merges of the deduplication recorded by the last build.
+    merges: Vec<Vec<String>>,
+}
+
+/// Chunks embedded before and replaced by the rebuild.
+enum StaleChunks {
+    /// The whole source is reindexed.
+    All,
+    /// The chunks of the changed files.
+    Paths(Vec<String>),
+}
+
 impl QAEmbedding {
     pub fn create(conf: &Config) -> Self {
         QAEmbedding { conf: conf.clone() }
     }
 
     /// Rebuild QA all embeddings.
+    /// The chunks embedded before are replaced, for the git source only the files changed
+    /// since the last indexed commit are re-embedded.
+    /// The duplicate chunks are removed before embedding, the files whose chunks were merged with
+    /// a changed file are re-embedded with it.
     pub async fn rebuild(&self) -> Result<()> {
         let conf = self.conf.clone();
-        let local_disk = llmchain::LocalDisk::create()?;
-        let markdown_loader = MarkdownLoader::create(local_disk.clone());
-        let directory_loader =
-            llmchain::DirectoryLoader::create(local_disk).with_loader("**/*.md", markdown_loader);
-        let documents = directory_loader
-            .load(DocumentPath::Str(conf.qa.path.clone()))
-            .await?;
-        info!("Step-1: parser all files:{}", documents.len());
+        let vector_store = QAVectorStore::create(&conf);
+        vector_store.init().await?;
+        let db = QADatabase::connect(&conf).await?;
+        db.init_index_meta().await?;
+        db.init_answer_table().await?;
+
+        let loader = QALoader::create(&conf.qa)?;
+        let deduplicator = QADeduplicator::create(&conf.qa)?;
+        let loaded = if conf.qa.git_url.is_empty() {
+            LoadedFiles {
+                documents: loader.load_directory(&conf.qa.path).await?,
+                root: conf.qa.path.clone(),
+                meta: QAIndexMeta::create(&conf.qa, &conf.qa.path),
+                stale: StaleChunks::All,
+                merges: vec![],
+            }
+        } else {
+            match self.load_git_source(&db, &loader).await? {
+                Some(val) => val,
+                None => return Ok(()),
+            }
+        };
+        info!("Step-1: parser all files:{}", loaded.documents.len());
+
+        let mut chunks = loader.split_documents(&loaded.documents)?;
+        info!("Step-2: split all files to:{}", chunks.len());
+        Self::add_metadata(&conf.qa, &loaded.root, &mut chunks);
+        let (chunks, groups) = deduplicator.dedup(chunks);
+        Self::log_duplicates(&groups);
+        info!("Step-2: deduplicate chunks to:{}", chunks.len());
+        let mut meta = loaded.meta;
+        match &loaded.stale {
+            StaleChunks::All => meta.record_merges(&[], &[], &groups),
+            StaleChunks::Paths(paths) => meta.record_merges(&loaded.merges, paths, &groups),
+        }
 
-        let documents = MarkdownSplitter::create().split_documents(&documents)?;
-        info!("Step-2: split all files to:{}", documents.len());
+        let fingerprint = QACheckpoint::fingerprint(
+            &format!("{}.{}", conf.qa.database, conf.qa.table),
+            conf.qa.embedding_batch_size,
+            &chunks,
+        );
+        let checkpoint = QACheckpoint::load(&conf.qa.rebuild_checkpoint, &fingerprint)?;
+        if checkpoint.is_resumed() {
+            // The stale chunks are deleted before the batches in the checkpoint were embedded.
+            info!(
+                "Resume the rebuild from {}, {} batches embedded",
+                conf.qa.rebuild_checkpoint,
+                checkpoint.done.len()
+            );
+        } else {
+            match &loaded.stale {
+                StaleChunks::All => db.delete_all_documents().await?,
+                StaleChunks::Paths(paths) => db.delete_documents(paths).await?,
+            }
+        }
 
         let now = Instant::now();
         info!(
             "Step-3: begin embedding to table:{}.{}",
             conf.qa.database, conf.qa.table
         );
-        let dsn = conf.qa.dsn.clone();
-        let databend_embedding = Arc::new(DatabendEmbedding::create(&dsn));
-        let databend_vector_store = DatabendVectorStore::create(&dsn, databend_embedding)
-            .with_database(&conf.qa.database)
-            .with_table(&conf.qa.table);
-        databend_vector_store.init().await?;
-
-        let _ = databend_vector_store.add_documents(&documents).await?;
+        let checkpoint = self
+            .embed_chunks(&vector_store, &chunks, checkpoint)
+            .await?;
         info!(
             "Step-3: finish embedding to table:{}.{}, cost {}",
             conf.qa.database,
             conf.qa.table,
             now.elapsed().as_secs()
         );
+
+        meta.documents = chunks.len();
+        db.insert_index_meta(&meta).await?;
+        checkpoint.remove()?;
         Ok(())
     }
+
+    /// Embed the chunks in batches with bounded concurrency, the batches in the checkpoint
+    /// are skipped and the embedded ones are added to it.
+    async fn embed_chunks(
+        &self,
+        vector_store: &QAVectorStore,
+        chunks: &[QAChunk],
+        checkpoint: QACheckpoint,
+    ) -> Result<QACheckpoint> {
+        let conf = &self.conf.qa;
+        let batches = chunks
+            .chunks(conf.embedding_batch_size.max(1))
+            .enumerate()
+            .collect::<Vec<_>>();
+        let total = batches.len();
+        // The pending batches of a resumed rebuild may be added before the interruption.
+        let resumed = checkpoint.is_resumed();
+        let pending = batches
+            .into_iter()
+            .filter(|(i, _)| !checkpoint.is_done(*i))
+            .collect::<Vec<_>>();
+        let embedded = Mutex::new((total - pending.len(), checkpoint));
+
+        let mut tasks = stream::iter(pending)
+            .map(|(i, batch)| {
+                let embedded = &embedded;
+                async move {
+                    self.embed_batch(vector_store, i, batch, resumed).await?;
+
+                    let mut embedded = embedded.lock().unwrap();
+                    embedded.1.mark_done(i)?;
+                    embedded.0 += 1;
+                    info!(
+                        "Step-3: embedded batch {}, {}/{} batches ({}%)",
+                        i,
+                        embedded.0,
+                        total,
+                        embedded.0 * 100 / total
+                    );
+                    Ok::<_, anyhow::Error>(())
+                }
+            })
+            .buffer_unordered(conf.embedding_concurrency.max(1));
+        while let Some(result) = tasks.next().await {
+            result?;
+        }
+        drop(tasks);
+
+        Ok(embedded.into_inner().unwrap().1)
+    }
+
+    async fn embed_batch(
+        &self,
+        vector_store: &QAVectorStore,
+        i: usize,
+        batch: &[QAChunk],
+        resumed: bool,
+    ) -> Result<()> {
+        let conf = &self.conf.qa;
+        let mut attempt = 0;
+        loop {
+            // A failed attempt may have added the batch before failing.
+            let result = if resumed || attempt > 0 {
+                vector_store.replace_chunks(batch).await
+            } else {
+                vector_store.add_chunks(batch).await
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < conf.embedding_max_retries => {
+                    let delay = Duration::from_millis(conf.embedding_retry_delay_ms)
+                        * 2u32.saturating_pow(attempt as u32);
+                    warn!(
+                        "Embedding batch {} failed, retry {}/{} in {:?}: {:?}",
+                        i,
+                        attempt + 1,
+                        conf.embedding_max_retries,
+                        delay,
+                        e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Load and split the whole corpus without writing to Databend.
+    pub async fn dry_run(&self) -> Result<QACorpusStats> {
+        let conf = &self.conf.qa;
+        let loader = QALoader::create(conf)?;
+        let path = if conf.git_url.is_empty() {
+            conf.path.clone()
+        } else {
+            let source = QAGitSource::create(conf);
+            let commit_sha = source.sync()?;
+            info!("Dry run of {} at {}", conf.git_url, commit_sha);
+            format!("{}/", source.root()?.display())
+        };
+
+        let documents = loader.load_directory(&path).await?;
+        info!("Step-1: parser all files:{}", documents.len());
+        let chunks = loader.split_documents(&documents)?;
+        info!("Step-2: split all files to:{}", chunks.len());
+        let (deduped, groups) = QADeduplicator::create(conf)?.dedup(chunks.clone());
+
+        // The duplicates are found before the deduplication merges them.
+        Ok(
+            QACorpusStats::create(&documents, &deduped, &loader.skipped_files())?
+                .with_duplicates(&chunks)
+                .with_merged(&groups),
+        )
+    }
+
+    /// The filterable metadata of the chunks: the document section and path under `qa.docs_root`
+    /// of the source root, and `qa.docs_version` for the pages without a version.
+    pub fn add_metadata(conf: &QAConfig, root: &str, chunks: &mut [QAChunk]) {
+        for chunk in chunks.iter_mut() {
+            chunk.locate(root, &conf.docs_root);
+            if chunk.version.is_empty() {
+                chunk.version = conf.docs_version.clone();
+            }
+        }
+    }
+
+    fn log_duplicates(groups: &[QADuplicateGroup]) {
+        for group in groups {
+            info!(
+                "dedup: keep {} [{}], merge {:?}",
+                group.kept.path,
+                group.kept.headings,
+                group.removed.iter().map(|x| &x.path).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    /// Sync the git source and load the files to embed, None if the index is up to date.
+    async fn load_git_source(
+        &self,
+        db: &QADatabase,
+        loader: &Arc<QALoader>,
+    ) -> Result<Option<LoadedFiles>> {
+        let qa = &self.conf.qa;
+        let source = QAGitSource::create(qa);
+        let commit_sha = source.sync()?;
+        let meta = QAIndexMeta {
+            git_branch: qa.git_branch.clone(),
+            git_subdir: qa.git_subdir.clone(),
+            commit_sha: commit_sha.clone(),
+            ..QAIndexMeta::create(qa, &qa.git_url)
+        };
+
+        let last = db
+            .latest_index_meta()
+            .await?
+            .filter(|x| {
+                x.source == meta.source
+                    && x.git_branch == meta.git_branch
+                    && x.git_subdir == meta.git_subdir
+                    && x.same_chunking(&meta)
+            })
+            .unwrap_or_default();
+        let last_sha = &last.commit_sha;
+        let changed_files = if last_sha.is_empty() {
+            None
+        } else {
+            source.changed_files(last_sha, &commit_sha)?
+        };
+
+        let root = format!("{}/", source.root()?.display());
+        let (documents, stale) = match changed_files {
+            None => {
+                info!("Index all files of {} at {}", qa.git_url, commit_sha);
+                // The files may be indexed before with other chunking parameters or deleted since.
+                (loader.load_directory(&root).await?, StaleChunks::All)
+            }
+            Some(files) if files.is_empty() => {
+                info!("Index is up to date with {} at {}", qa.git_url, commit_sha);
+                return Ok(None);
+            }
+            Some(files) => {
+                info!(
+                    "Index {} files changed from {} to {}",
+                    files.len(),
+                    last_sha,
+                    commit_sha
+                );
+                let paths = files
+                    .iter()
+                    .map(|x| x.display().to_string())
+                    .collect::<Vec<_>>();
+                // The chunks of the unchanged files may be merged into the changed ones before.
+                let paths = last.merged_paths(&paths);
+                let files = paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+                (loader.load_files(&files).await?, StaleChunks::Paths(paths))
+            }
+        };
+        Ok(Some(LoadedFiles {
+            documents,
+            root,
+            meta,
+            stale,
+            merges: last.dedup_merges,
+        }))
+    }
 }
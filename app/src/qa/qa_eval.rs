@@ -0,0 +1,347 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::DatabendLLM;
+use llmchain::LLM;
+use log::info;
+use log::warn;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::qa::QAAnswer;
+use crate::qa::QAChunk;
+use crate::qa::QAExtractiveLLM;
+use crate::Config;
+use crate::QALLM;
+
+/// Phrases of the answers declining the question, as the answer prompt asks to say
+/// `you don't know`, the empty answers are replied with `QAAnswer::FALLBACK`.
+const NO_ANSWER_PHRASES: [&str; 3] = ["don't know", "dont know", "do not know"];
+
+/// LLM of the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QAEvalLLM {
+    /// The Databend LLM of the dsn.
+    Databend,
+    /// The local extractive LLM, answering with the contexts of the prompt.
+    Extractive,
+}
+
+impl FromStr for QAEvalLLM {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "databend" | "" => Ok(QAEvalLLM::Databend),
+            "extractive" => Ok(QAEvalLLM::Extractive),
+            _ => Err(anyhow!(
+                "unknown eval llm: {}, expect databend|extractive",
+                s
+            )),
+        }
+    }
+}
+
+/// A question of the golden set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QAEvalCase {
+    pub question: String,
+    /// Documents expected to be retrieved, matched by the end of the chunk path or the slug.
+    pub sources: Vec<String>,
+    /// Facts or SQL expected in the answer, matched case insensitive.
+    pub facts: Vec<String>,
+    /// The question is not answered by the docs, the answer should decline it.
+    pub no_answer: bool,
+}
+
+/// The evaluation of a question.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QAEvalResult {
+    pub question: String,
+    pub answer: String,
+    pub expected_sources: Vec<String>,
+    /// Documents of the chunks, in rank order.
+    pub retrieved: Vec<String>,
+    /// Fraction of the expected sources in the top `k` documents.
+    pub recall: f64,
+    /// 1 / rank of the first expected source, 0 if none is retrieved.
+    pub reciprocal_rank: f64,
+    pub facts_found: Vec<String>,
+    pub facts_missing: Vec<String>,
+    pub no_answer_expected: bool,
+    /// The answer declines the question.
+    pub no_answer: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl QAEvalResult {
+    /// Evaluate the answer of the case, the documents in the top `k` count for the recall.
+    pub fn create(case: &QAEvalCase, answer: Result<QAAnswer>, k: usize) -> Self {
+        let mut result = QAEvalResult {
+            question: case.question.clone(),
+            expected_sources: case.sources.clone(),
+            no_answer_expected: case.no_answer,
+            ..Default::default()
+        };
+        let answer = match answer {
+            Ok(answer) => answer,
+            Err(e) => {
+                result.error = Some(e.to_string());
+                result.facts_missing = case.facts.clone();
+                return result;
+            }
+        };
+
+        // The chunks in the prompt first, then the other retrieved ones.
+        for chunk in answer
+            .explain
+            .chunks
+            .iter()
+            .chain(&answer.explain.retrieved)
+        {
+            let document = Self::document(chunk);
+            if !result.retrieved.contains(&document) {
+                result.retrieved.push(document);
+            }
+        }
+        if !case.sources.is_empty() {
+            let ranks = case
+                .sources
+                .iter()
+                .filter_map(|source| {
+                    result
+                        .retrieved
+                        .iter()
+                        .position(|x| Self::is_source(x, source))
+                })
+                .collect::<Vec<_>>();
+            let found = ranks.iter().filter(|x| **x < k).count();
+            result.recall = round(found as f64 / case.sources.len() as f64);
+            result.reciprocal_rank = match ranks.iter().min() {
+                Some(rank) => round(1.0 / (*rank + 1) as f64),
+                None => 0.0,
+            };
+        }
+
+        let normalized = normalize(&answer.answer);
+        for fact in &case.facts {
+            if normalized.contains(&normalize(fact)) {
+                result.facts_found.push(fact.clone());
+            } else {
+                result.facts_missing.push(fact.clone());
+            }
+        }
+
+        let lower = answer.answer.to_lowercase();
+        result.no_answer = answer.answer.trim().is_empty()
+            || answer.answer.trim() == QAAnswer::FALLBACK
+            || NO_ANSWER_PHRASES.iter().any(|x| lower.contains(x));
+        result.answer = answer.answer;
+        result
+    }
+
+    /// The document of the chunk, its slug if set.
+    fn document(chunk: &QAChunk) -> String {
+        match chunk.slug.as_str() {
+            "" => chunk.path.clone(),
+            slug => slug.to_string(),
+        }
+    }
+
+    fn is_source(document: &str, source: &str) -> bool {
+        let source = source.trim_matches('/');
+        !source.is_empty() && document.trim_end_matches('/').ends_with(source)
+    }
+}
+
+/// Metrics of the golden set, with the result of each question.
+/// The report has no timestamps or timings, so the reports of two configs can be diffed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QAEvalReport {
+    pub questions: usize,
+    pub errors: usize,
+    pub k: usize,
+    /// Mean recall@k of the questions with sources.
+    pub recall_at_k: f64,
+    /// Mean reciprocal rank of the questions with sources.
+    pub mrr: f64,
+    /// Mean fraction of the facts found in the answers of the questions with facts.
+    pub fact_coverage: f64,
+    /// Fraction of the declined answers expected to be declined, None if no answer is declined.
+    pub no_answer_precision: Option<f64>,
+    /// Answers declining the question.
+    pub no_answers: usize,
+    pub results: Vec<QAEvalResult>,
+}
+
+impl QAEvalReport {
+    /// The metrics of the results, the failed questions count as missed.
+    pub fn create(k: usize, results: Vec<QAEvalResult>) -> Self {
+        let with_sources = results
+            .iter()
+            .filter(|x| !x.expected_sources.is_empty())
+            .collect::<Vec<_>>();
+        let with_facts = results
+            .iter()
+            .filter(|x| !x.facts_found.is_empty() || !x.facts_missing.is_empty())
+            .collect::<Vec<_>>();
+        let declined = results
+            .iter()
+            .filter(|x| x.error.is_none() && x.no_answer)
+            .collect::<Vec<_>>();
+
+        QAEvalReport {
+            questions: results.len(),
+            errors: results.iter().filter(|x| x.error.is_some()).count(),
+            k,
+            recall_at_k: mean(with_sources.iter().map(|x| x.recall)),
+            mrr: mean(with_sources.iter().map(|x| x.reciprocal_rank)),
+            fact_coverage: mean(with_facts.iter().map(|x| {
+                x.facts_found.len() as f64 / (x.facts_found.len() + x.facts_missing.len()) as f64
+            })),
+            no_answer_precision: match declined.len() {
+                0 => None,
+                n => Some(round(
+                    declined.iter().filter(|x| x.no_answer_expected).count() as f64 / n as f64,
+                )),
+            },
+            no_answers: declined.len(),
+            results,
+        }
+    }
+
+    /// Pretty JSON, stable for the diffs.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn summary(&self) -> String {
+        let precision = match self.no_answer_precision {
+            Some(x) => x.to_string(),
+            None => "-".to_string(),
+        };
+        format!(
+            "questions: {}, errors: {}\nrecall@{}: {}\nmrr: {}\nfact coverage: {}\nno-answer precision: {} ({} declined)",
+            self.questions,
+            self.errors,
+            self.k,
+            self.recall_at_k,
+            self.mrr,
+            self.fact_coverage,
+            precision,
+            self.no_answers
+        )
+    }
+}
+
+/// Runs the golden set through the QA pipeline.
+pub struct QAEval {
+    conf: Config,
+    llm_name: String,
+    llm: Arc<dyn LLM>,
+}
+
+impl QAEval {
+    pub fn create(conf: &Config) -> Result<Self> {
+        let (llm_name, llm): (&str, Arc<dyn LLM>) = match QAEvalLLM::from_str(&conf.qa.eval_llm)? {
+            QAEvalLLM::Databend => ("databend", DatabendLLM::create(&conf.qa.dsn)),
+            QAEvalLLM::Extractive => ("extractive", Arc::new(QAExtractiveLLM::create())),
+        };
+        // The golden questions and their answers are not logged with the real ones.
+        let mut conf = conf.clone();
+        conf.qa.answer_table.clear();
+        Ok(QAEval {
+            conf,
+            llm_name: llm_name.to_string(),
+            llm,
+        })
+    }
+
+    /// The config of the eval, without the `answer_table`.
+    pub fn conf(&self) -> &Config {
+        &self.conf
+    }
+
+    /// The cases of the JSONL golden set, the blank lines are skipped.
+    pub fn load_cases(path: &str) -> Result<Vec<QAEvalCase>> {
+        let content = fs::read_to_string(path)?;
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| anyhow!("invalid case at {}:{}: {}", path, i + 1, e))
+            })
+            .collect()
+    }
+
+    /// Answer each question in turn, a failed question is reported with its error.
+    pub async fn run(&self, cases: &[QAEvalCase]) -> QAEvalReport {
+        let qa_llm = QALLM::create(&self.conf).with_llm(&self.llm_name, self.llm.clone());
+        let mut results = vec![];
+        for (i, case) in cases.iter().enumerate() {
+            info!("eval {}/{}: {}", i + 1, cases.len(), case.question);
+            let answer = qa_llm.explain(&case.question, None).await;
+            if let Err(e) = &answer {
+                warn!("Failed to answer {:?}: {:?}", case.question, e);
+            }
+            results.push(QAEvalResult::create(case, answer, self.conf.qa.top));
+        }
+        QAEvalReport::create(self.conf.qa.top, results)
+    }
+
+    /// Run the golden set of the config and write the report.
+    pub async fn eval(&self) -> Result<QAEvalReport> {
+        let cases = Self::load_cases(&self.conf.qa.eval)?;
+        let report = self.run(&cases).await;
+
+        let path = Path::new(&self.conf.qa.eval_report);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, report.to_json()? + "\n")?;
+        info!("eval report written to {}", self.conf.qa.eval_report);
+        Ok(report)
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Rounded to 4 decimals, so the reports differ only by real changes.
+fn round(x: f64) -> f64 {
+    (x * 10000.0).round() / 10000.0
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let values = values.collect::<Vec<_>>();
+    if values.is_empty() {
+        return 0.0;
+    }
+    round(values.iter().sum::<f64>() / values.len() as f64)
+}
@@ -0,0 +1,137 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Serialize;
+use url::Url;
+
+use crate::configs::QAConfig;
+use crate::qa::QAChunk;
+use crate::qa::QASearchFilter;
+
+/// The answer of a question with how it was made.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QAAnswer {
+    pub answer: String,
+    pub explain: QAExplain,
+}
+
+impl QAAnswer {
+    /// Reply of the server to an empty answer.
+    pub const FALLBACK: &'static str = "Sorry, I dont know how to help with that.";
+}
+
+/// How the answer was made, returned to the admins by the debug queries.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QAExplain {
+    pub question: String,
+    /// The question followed by its rewrites.
+    pub queries: Vec<String>,
+    /// Chunks of the vector search with their scores, best first.
+    pub retrieved: Vec<QAChunk>,
+    /// Chunks in the prompt after the rerank and the context budget.
+    pub chunks: Vec<QAChunk>,
+    pub prompt: String,
+    pub params: QAExplainParams,
+    pub tokens: QAExplainTokens,
+    pub timings: QAExplainTimings,
+}
+
+/// Parameters of the model, the retrieval and the prompt.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QAExplainParams {
+    /// The LLM answering, `databend` or the `eval_llm`.
+    pub llm: String,
+    /// The Databend functions of the answer and the embeddings, empty if not used.
+    /// Their model is picked by the warehouse and is not reported by Databend.
+    pub completion_function: String,
+    pub embedding_function: String,
+    /// Host of the `dsn`, without the credentials.
+    pub warehouse: String,
+    /// Collections searched together, empty for the collection of the config.
+    pub collections: Vec<String>,
+    pub table: String,
+    pub top: usize,
+    pub query_rewrite: bool,
+    pub query_rewrite_max: usize,
+    pub rerank: String,
+    pub rerank_candidates: usize,
+    pub context_max_tokens: usize,
+    pub prompt_max_tokens: usize,
+    pub context_overflow: String,
+    pub context_parent_section: bool,
+    pub context_neighbors: usize,
+    pub filter: QASearchFilter,
+}
+
+impl QAExplainParams {
+    pub fn create(conf: &QAConfig, collections: Option<&[String]>) -> Self {
+        QAExplainParams {
+            llm: "databend".to_string(),
+            completion_function: "ai_text_completion".to_string(),
+            embedding_function: "ai_embedding_vector".to_string(),
+            warehouse: Url::parse(&conf.dsn)
+                .ok()
+                .and_then(|x| x.host_str().map(|x| x.to_string()))
+                .unwrap_or_default(),
+            collections: collections.map(|x| x.to_vec()).unwrap_or_default(),
+            table: format!("{}.{}", conf.database, conf.table),
+            top: conf.top,
+            query_rewrite: conf.query_rewrite,
+            query_rewrite_max: conf.query_rewrite_max,
+            rerank: conf.rerank.clone(),
+            rerank_candidates: conf.rerank_candidates,
+            context_max_tokens: conf.context_max_tokens,
+            prompt_max_tokens: conf.prompt_max_tokens,
+            context_overflow: conf.context_overflow.clone(),
+            context_parent_section: conf.context_parent_section,
+            context_neighbors: conf.context_neighbors,
+            filter: QASearchFilter::default(),
+        }
+    }
+
+    /// The answers are made by another LLM than the Databend one.
+    pub fn with_llm(mut self, llm: &str) -> Self {
+        if llm != "databend" {
+            self.completion_function.clear();
+        }
+        self.llm = llm.to_string();
+        self
+    }
+}
+
+/// Token counts of the prompt and the answer.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QAExplainTokens {
+    pub prompt: usize,
+    /// Tokens of the contexts in the prompt.
+    pub context: usize,
+    pub completion: usize,
+    /// Chunks truncated or summarized to fit the context budget.
+    pub shortened_chunks: usize,
+    /// Chunks dropped for the context budget.
+    pub dropped_chunks: usize,
+    /// Chunks of the same documents merged into the chunks.
+    pub expanded_chunks: usize,
+}
+
+/// Milliseconds of each stage of the answer.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QAExplainTimings {
+    pub rewrite_ms: u64,
+    pub search_ms: u64,
+    pub rerank_ms: u64,
+    pub context_ms: u64,
+    pub generate_ms: u64,
+    pub total_ms: u64,
+}
@@ -0,0 +1,55 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::EmbeddingResult;
+use llmchain::GenerateResult;
+use llmchain::LLM;
+
+/// Separator of the contexts in the answer prompt.
+const CONTEXTS_SEPARATOR: &str = "=========\n";
+
+/// Local LLM answering with the contexts of the prompt, for the offline evaluation.
+/// It replies nothing to the other prompts, and has no embeddings.
+pub struct QAExtractiveLLM {}
+
+impl QAExtractiveLLM {
+    pub fn create() -> Self {
+        QAExtractiveLLM {}
+    }
+
+    /// The contexts between the separators of the answer prompt, empty for the other prompts.
+    pub fn contexts(prompt: &str) -> &str {
+        let mut parts = prompt.splitn(3, CONTEXTS_SEPARATOR);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(_), Some(contexts), Some(_)) => contexts.trim(),
+            _ => "",
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LLM for QAExtractiveLLM {
+    async fn embedding(&self, _inputs: Vec<String>) -> Result<EmbeddingResult> {
+        Err(anyhow!("the extractive LLM has no embeddings"))
+    }
+
+    async fn generate(&self, input: &str) -> Result<GenerateResult> {
+        Ok(GenerateResult {
+            generation: Self::contexts(input).to_string(),
+            ..Default::default()
+        })
+    }
+}
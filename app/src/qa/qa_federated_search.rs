@@ -0,0 +1,110 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use futures::future::try_join_all;
+use log::info;
+
+use crate::configs::DEFAULT_COLLECTION;
+use crate::qa::QAChunk;
+use crate::qa::QASearchFilter;
+use crate::qa::QAVectorStore;
+use crate::Config;
+
+/// Search several collections in parallel and merge their chunks by the weighted scores.
+pub struct QAFederatedSearch {
+    conf: Config,
+    top: Option<usize>,
+    filter: QASearchFilter,
+}
+
+impl QAFederatedSearch {
+    pub fn create(conf: &Config) -> Self {
+        QAFederatedSearch {
+            conf: conf.clone(),
+            top: None,
+            filter: QASearchFilter::default(),
+        }
+    }
+
+    /// Search `top` chunks of each collection and keep the `top` merged ones.
+    pub fn with_top(mut self, top: usize) -> Self {
+        self.top = Some(top);
+        self
+    }
+
+    /// Only search the chunks matching the filter.
+    pub fn with_filter(mut self, filter: &QASearchFilter) -> Self {
+        self.filter = filter.clone();
+        self
+    }
+
+    /// The top chunks of the collections, labeled with their collection, best first.
+    /// Each collection returns its own `top` chunks, the merged `qa.top` ones are kept.
+    pub async fn search(&self, question: &str, collections: &[String]) -> Result<Vec<QAChunk>> {
+        let confs = collections
+            .iter()
+            .map(|x| self.conf.with_collection(x))
+            .collect::<Result<Vec<_>>>()?;
+
+        let searches = collections
+            .iter()
+            .zip(confs.iter())
+            .map(|(name, conf)| async move {
+                let vector_store = QAVectorStore::create(conf);
+                let top = self.top.unwrap_or(conf.qa.top);
+                let mut chunks = vector_store
+                    .filtered_search(question, &self.filter, 0, top)
+                    .await?;
+                let name = if name.is_empty() {
+                    DEFAULT_COLLECTION
+                } else {
+                    name.as_str()
+                };
+                info!("federated search found {} chunks in {}", chunks.len(), name);
+
+                for chunk in chunks.iter_mut() {
+                    chunk.collection = name.to_string();
+                    chunk.score = Self::score(
+                        chunk.similarity,
+                        vector_store.min_similarity(),
+                        conf.qa.search_weight,
+                    );
+                }
+                Ok::<_, anyhow::Error>(chunks)
+            });
+        let results = try_join_all(searches).await?;
+
+        Ok(Self::merge(results, self.top.unwrap_or(self.conf.qa.top)))
+    }
+
+    /// The similarity normalized from `[min_similarity, 1]` to `[0, 1]`, then weighted.
+    pub fn score(similarity: f32, min_similarity: f32, weight: usize) -> f32 {
+        let normalized = if min_similarity < 1.0 {
+            ((similarity - min_similarity) / (1.0 - min_similarity)).clamp(0.0, 1.0)
+        } else {
+            similarity
+        };
+        normalized * weight as f32 / 100.0
+    }
+
+    /// Merge the chunks of the collections, keep the top ones by score.
+    pub fn merge(results: Vec<Vec<QAChunk>>, top: usize) -> Vec<QAChunk> {
+        let mut chunks = results.into_iter().flatten().collect::<Vec<_>>();
+        // The stable sort keeps the collection order for the same score.
+        chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        chunks.truncate(top);
+        chunks
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// YAML front matter of a markdown page, only the flat `key: value` fields and the `tags` list are parsed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QAFrontMatter {
+    pub title: String,
+    pub sidebar_label: String,
+    pub slug: String,
+    pub description: String,
+    /// Product version of the page.
+    pub version: String,
+    /// `tags: [a, b]`, `tags: a, b` or the `- a` lines after `tags:`.
+    pub tags: Vec<String>,
+}
+
+impl QAFrontMatter {
+    /// Split the front matter from the page, the page is returned as is if it has no front matter.
+    pub fn parse(content: &str) -> (Self, &str) {
+        let mut front_matter = QAFrontMatter::default();
+
+        let rest = match content.trim_start_matches('\u{feff}').strip_prefix("---") {
+            Some(rest) if rest.starts_with('\n') || rest.starts_with("\r\n") => rest,
+            _ => return (front_matter, content),
+        };
+        let (yaml, body) = match rest.find("\n---") {
+            Some(end) => {
+                let body = &rest[end + 4..];
+                let body = body.split_once('\n').map(|(_, x)| x).unwrap_or_default();
+                (&rest[..end], body)
+            }
+            None => return (front_matter, content),
+        };
+
+        // The `- item` lines follow an empty `tags:`.
+        let mut in_tags = false;
+        for line in yaml.lines() {
+            if in_tags {
+                if let Some(tag) = line.trim().strip_prefix("- ") {
+                    front_matter.tags.push(Self::unquote(tag));
+                    continue;
+                }
+            }
+            in_tags = false;
+
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) if !key.starts_with(char::is_whitespace) => (key, value),
+                _ => continue,
+            };
+            let value = Self::unquote(value);
+            match key.trim() {
+                "title" => front_matter.title = value,
+                "sidebar_label" => front_matter.sidebar_label = value,
+                "slug" => front_matter.slug = value,
+                "description" => front_matter.description = value,
+                "version" => front_matter.version = value,
+                "tags" => {
+                    let list = value.trim_start_matches('[').trim_end_matches(']');
+                    front_matter.tags = list
+                        .split(',')
+                        .map(Self::unquote)
+                        .filter(|x| !x.is_empty())
+                        .collect();
+                    in_tags = value.is_empty();
+                }
+                _ => {}
+            }
+        }
+        (front_matter, body)
+    }
+
+    fn unquote(value: &str) -> String {
+        let value = value.trim();
+        value
+            .strip_prefix('"')
+            .and_then(|x| x.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')))
+            .unwrap_or(value)
+            .to_string()
+    }
+
+    /// Title of the page, `title` or `sidebar_label`.
+    pub fn page_title(&self) -> &str {
+        if self.title.is_empty() {
+            &self.sidebar_label
+        } else {
+            &self.title
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use git2::build::RepoBuilder;
+use git2::Oid;
+use git2::Repository;
+use git2::ResetType;
+use log::info;
+
+use crate::configs::QAConfig;
+
+/// Docs source of a git repo, the repo is cloned to a local checkout and fetched on every sync.
+pub struct QAGitSource {
+    url: String,
+    branch: String,
+    subdir: String,
+    checkout_dir: PathBuf,
+}
+
+impl QAGitSource {
+    pub fn create(conf: &QAConfig) -> Self {
+        QAGitSource {
+            url: conf.git_url.clone(),
+            branch: conf.git_branch.clone(),
+            subdir: conf.git_subdir.trim_matches('/').to_string(),
+            checkout_dir: PathBuf::from(&conf.git_checkout_dir),
+        }
+    }
+
+    /// Clone or fetch the branch and check it out, return the commit sha of the branch head.
+    pub fn sync(&self) -> Result<String> {
+        let repo = if self.checkout_dir.join(".git").exists() {
+            let repo = Repository::open(&self.checkout_dir)?;
+            repo.remote_set_url("origin", &self.url)?;
+            repo
+        } else {
+            info!("Cloning {} to {:?}", self.url, self.checkout_dir);
+            std::fs::create_dir_all(&self.checkout_dir)?;
+            RepoBuilder::new()
+                .branch(&self.branch)
+                .clone(&self.url, &self.checkout_dir)
+                .with_context(|| format!("failed to clone {}", self.url))?
+        };
+
+        let refspec = format!(
+            "+refs/heads/{}:refs/remotes/origin/{}",
+            self.branch, self.branch
+        );
+        repo.find_remote("origin")?
+            .fetch(&[refspec], None, None)
+            .with_context(|| format!("failed to fetch {} of {}", self.branch, self.url))?;
+
+        let oid = repo.refname_to_id(&format!("refs/remotes/origin/{}", self.branch))?;
+        let commit = repo.find_commit(oid)?;
+        repo.reset(commit.as_object(), ResetType::Hard, None)?;
+        info!("Synced {} {} to {}", self.url, self.branch, oid);
+        Ok(oid.to_string())
+    }
+
+    /// Local directory of the docs in the checkout.
+    pub fn root(&self) -> Result<PathBuf> {
+        let root = self.checkout_dir.join(&self.subdir);
+        root.canonicalize()
+            .with_context(|| format!("git source directory not found: {:?}", root))
+    }
+
+    /// Files under the docs directory changed between the two commits, including the deleted ones.
+    /// None if the old commit is unknown to the checkout, eg: after a force push.
+    pub fn changed_files(&self, from_sha: &str, to_sha: &str) -> Result<Option<Vec<PathBuf>>> {
+        let repo = Repository::open(&self.checkout_dir)?;
+        let old_tree = match Oid::from_str(from_sha).and_then(|x| repo.find_commit(x)) {
+            Ok(commit) => commit.tree()?,
+            Err(_) => return Ok(None),
+        };
+        let new_tree = repo.find_commit(Oid::from_str(to_sha)?)?.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+        let checkout_dir = self.checkout_dir.canonicalize()?;
+        let subdir = Path::new(&self.subdir);
+        let mut files = vec![];
+        for delta in diff.deltas() {
+            for file in [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+            {
+                let path = checkout_dir.join(file);
+                if file.starts_with(subdir) && !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+        }
+        Ok(Some(files))
+    }
+}
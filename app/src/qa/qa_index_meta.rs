@@ -0,0 +1,140 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::configs::QAConfig;
+use crate::qa::QADuplicateGroup;
+
+/// Version of the chunk metadata columns, the chunks embedded with another version are all re-embedded.
+const METADATA_VERSION: usize = 1;
+
+/// Metadata of an index build, saved as json in the `<table>_meta` table.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct QAIndexMeta {
+    /// `qa.path` of the local source, or `qa.git_url` of the git source.
+    pub source: String,
+    pub git_branch: String,
+    pub git_subdir: String,
+    /// Commit of the git source indexed, empty for the local source.
+    pub commit_sha: String,
+    /// Documents embedded in the build.
+    pub documents: usize,
+    /// Chunking parameters of the files.
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub min_chunk_size: usize,
+    pub split_code_blocks: bool,
+    pub prepend_headings: bool,
+    /// Metadata of the chunks.
+    pub metadata_version: usize,
+    pub docs_root: String,
+    pub docs_version: String,
+    /// Deduplication of the chunks.
+    pub dedup: String,
+    pub dedup_similarity: usize,
+    pub dedup_keep: String,
+    pub dedup_prefer_paths: Vec<String>,
+    /// Documents whose chunks are merged together by the deduplication, an incremental build
+    /// re-embeds all the documents of a merge if one of them changes.
+    pub dedup_merges: Vec<Vec<String>>,
+}
+
+impl QAIndexMeta {
+    /// The meta of the build with the chunking parameters of the config.
+    pub fn create(conf: &QAConfig, source: &str) -> Self {
+        QAIndexMeta {
+            source: source.to_string(),
+            chunk_size: conf.chunk_size,
+            chunk_overlap: conf.chunk_overlap,
+            min_chunk_size: conf.min_chunk_size,
+            split_code_blocks: conf.split_code_blocks,
+            prepend_headings: conf.prepend_headings,
+            metadata_version: METADATA_VERSION,
+            docs_root: conf.docs_root.clone(),
+            docs_version: conf.docs_version.clone(),
+            dedup: conf.dedup.clone(),
+            dedup_similarity: conf.dedup_similarity,
+            dedup_keep: conf.dedup_keep.clone(),
+            dedup_prefer_paths: conf.dedup_prefer_paths.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the chunks of the two builds are split with the same parameters and metadata.
+    pub fn same_chunking(&self, other: &QAIndexMeta) -> bool {
+        self.chunk_size == other.chunk_size
+            && self.chunk_overlap == other.chunk_overlap
+            && self.min_chunk_size == other.min_chunk_size
+            && self.split_code_blocks == other.split_code_blocks
+            && self.prepend_headings == other.prepend_headings
+            && self.metadata_version == other.metadata_version
+            && self.docs_root == other.docs_root
+            && self.docs_version == other.docs_version
+            && self.dedup == other.dedup
+            && self.dedup_similarity == other.dedup_similarity
+            && self.dedup_keep == other.dedup_keep
+            && self.dedup_prefer_paths == other.dedup_prefer_paths
+    }
+
+    /// The paths with the documents merged with them by the deduplication, transitively.
+    pub fn merged_paths(&self, paths: &[String]) -> Vec<String> {
+        let mut result = paths.to_vec();
+        let mut seen = paths.iter().cloned().collect::<HashSet<_>>();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for merge in &self.dedup_merges {
+                if merge.iter().any(|x| seen.contains(x)) {
+                    for path in merge {
+                        if seen.insert(path.clone()) {
+                            result.push(path.clone());
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Keep the merges of the documents not re-embedded and add the merges of the build.
+    pub fn record_merges(
+        &mut self,
+        previous: &[Vec<String>],
+        embedded: &[String],
+        groups: &[QADuplicateGroup],
+    ) {
+        self.dedup_merges = previous
+            .iter()
+            .filter(|x| !x.iter().any(|path| embedded.contains(path)))
+            .cloned()
+            .collect();
+        for group in groups {
+            let mut paths = vec![];
+            for chunk in std::iter::once(&group.kept).chain(group.removed.iter()) {
+                if !paths.contains(&chunk.path) {
+                    paths.push(chunk.path.clone());
+                }
+            }
+            if paths.len() > 1 {
+                self.dedup_merges.push(paths);
+            }
+        }
+    }
+}
@@ -13,71 +13,346 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
+use anyhow::bail;
 use anyhow::Result;
-use llmchain::DatabendEmbedding;
+use futures::future::try_join_all;
 use llmchain::DatabendLLM;
-use llmchain::DatabendVectorStore;
 use llmchain::DocumentRetrievalPrompt;
 use llmchain::Prompt;
-use llmchain::VectorStore;
 use llmchain::LLM;
+use log::debug;
 use log::info;
+use log::warn;
+use tokio::time::Instant;
 
+use crate::qa::QAAnswer;
+use crate::qa::QAChunk;
+use crate::qa::QAContextBuilder;
+use crate::qa::QAContextOverflow;
+use crate::qa::QADatabase;
+use crate::qa::QAExplain;
+use crate::qa::QAExplainParams;
+use crate::qa::QAExplainTokens;
+use crate::qa::QAFederatedSearch;
+use crate::qa::QAQueryRewriter;
+use crate::qa::QAReranker;
+use crate::qa::QARerankerKind;
+use crate::qa::QASearchFilter;
+use crate::qa::QAVectorStore;
 use crate::Config;
 
 pub struct QALLM {
     conf: Config,
+    llm: Arc<dyn LLM>,
+    llm_name: String,
+    filter: QASearchFilter,
 }
 
 impl QALLM {
     pub fn create(conf: &Config) -> Self {
-        QALLM { conf: conf.clone() }
+        QALLM {
+            conf: conf.clone(),
+            llm: DatabendLLM::create(&conf.qa.dsn),
+            llm_name: "databend".to_string(),
+            filter: QASearchFilter::default(),
+        }
+    }
+
+    /// Use the LLM to rewrite, rerank and answer instead of the Databend one,
+    /// the name is reported by the debug queries.
+    pub fn with_llm(mut self, name: &str, llm: Arc<dyn LLM>) -> Self {
+        self.llm = llm;
+        self.llm_name = name.to_string();
+        self
+    }
+
+    /// Only retrieve the chunks matching the metadata filter.
+    pub fn with_filter(mut self, filter: &QASearchFilter) -> Self {
+        self.filter = filter.clone();
+        self
     }
 
     pub async fn query(&self, question: &str) -> Result<String> {
-        let dsn = self.conf.qa.dsn.clone();
-        let topk = self.conf.qa.top;
+        Ok(self.explain(question, None).await?.answer)
+    }
+
+    /// Answer from the top chunks of several collections, each chunk labeled with its collection.
+    pub async fn federated_query(&self, question: &str, collections: &[String]) -> Result<String> {
+        Ok(self.explain(question, Some(collections)).await?.answer)
+    }
 
-        info!("question: {}", question);
+    /// Answer with the chunks, prompt, token counts and timings, the collections are searched together if set.
+    pub async fn explain(
+        &self,
+        question: &str,
+        collections: Option<&[String]>,
+    ) -> Result<QAAnswer> {
+        match collections {
+            None => info!("question: {}", question),
+            Some(collections) => info!("question: {}, collections: {:?}", question, collections),
+        }
+        let start = Instant::now();
+        let mut explain = QAExplain {
+            question: question.to_string(),
+            params: QAExplainParams {
+                filter: self.filter.clone(),
+                ..QAExplainParams::create(&self.conf.qa, collections).with_llm(&self.llm_name)
+            },
+            ..Default::default()
+        };
 
-        // create embedding.
-        let databend_embedding = Arc::new(DatabendEmbedding::create(&dsn));
+        let reranker = QARerankerKind::create(&self.conf, self.llm.clone())?;
+        let now = Instant::now();
+        let queries = if self.conf.qa.query_rewrite {
+            QAQueryRewriter::create(self.llm.clone())
+                .with_max_queries(self.conf.qa.query_rewrite_max)
+                .rewrite(question)
+                .await
+        } else {
+            vec![question.to_string()]
+        };
+        explain.timings.rewrite_ms = now.elapsed().as_millis() as u64;
 
-        // create databend vector store.
-        let databend_vector_store = DatabendVectorStore::create(&dsn, databend_embedding)
-            .with_database(&self.conf.qa.database)
-            .with_table(&self.conf.qa.table);
-        let similarities = databend_vector_store
-            .similarity_search(question, topk)
-            .await?;
+        let now = Instant::now();
+        let top = self.candidates(&reranker);
+        let searches = queries
+            .iter()
+            .map(|query| self.search(query, collections, &reranker));
+        let results = try_join_all(searches).await?;
+        let similarities = QAQueryRewriter::merge(results, top);
+        explain.timings.search_ms = now.elapsed().as_millis() as u64;
 
-        info!("similarities: {:?}", similarities);
+        debug!("similarities: {:?}", similarities);
+        explain.retrieved = similarities.clone();
+
+        let now = Instant::now();
+        let similarities = self.rerank(&reranker, question, similarities).await?;
+        explain.timings.rerank_ms = now.elapsed().as_millis() as u64;
+
+        explain.queries = queries;
+        let answer = self.answer(question, similarities, &mut explain).await?;
+        explain.timings.total_ms = start.elapsed().as_millis() as u64;
+        Ok(QAAnswer { answer, explain })
+    }
+
+    /// The chunks of the query, the `score` is the similarity, or the weighted one if federated.
+    async fn search(
+        &self,
+        query: &str,
+        collections: Option<&[String]>,
+        reranker: &Option<Arc<dyn QAReranker>>,
+    ) -> Result<Vec<QAChunk>> {
+        match collections {
+            None => {
+                let vector_store = QAVectorStore::create(&self.conf);
+                let mut chunks = vector_store
+                    .filtered_search(query, &self.filter, 0, self.candidates(reranker))
+                    .await?;
+                for chunk in chunks.iter_mut() {
+                    chunk.score = chunk.similarity;
+                }
+                Ok(chunks)
+            }
+            Some(collections) => {
+                let mut search = QAFederatedSearch::create(&self.conf).with_filter(&self.filter);
+                if reranker.is_some() {
+                    search = search.with_top(self.candidates(reranker));
+                }
+                search.search(query, collections).await
+            }
+        }
+    }
+
+    /// Chunks to fetch from the vector search, more than `top` if they are reranked.
+    fn candidates(&self, reranker: &Option<Arc<dyn QAReranker>>) -> usize {
+        match reranker {
+            Some(_) => self.conf.qa.rerank_candidates.max(self.conf.qa.top),
+            None => self.conf.qa.top,
+        }
+    }
 
-        let contexts = similarities
+    async fn rerank(
+        &self,
+        reranker: &Option<Arc<dyn QAReranker>>,
+        question: &str,
+        chunks: Vec<QAChunk>,
+    ) -> Result<Vec<QAChunk>> {
+        match reranker {
+            Some(reranker) => reranker.rerank(question, chunks, self.conf.qa.top).await,
+            None => Ok(chunks),
+        }
+    }
+
+    async fn answer(
+        &self,
+        question: &str,
+        chunks: Vec<QAChunk>,
+        explain: &mut QAExplain,
+    ) -> Result<String> {
+        let now = Instant::now();
+        let instructions = self
+            .conf
+            .qa
+            .instructions
             .iter()
-            .map(|x| format!("context:{}\nsource:{}", x.content, x.path))
-            .collect::<Vec<_>>()
-            .join("");
-
-        let prompt_template = DocumentRetrievalPrompt::create().with_instructions(vec!["Present your answer in markdown format, including code snippets if have, format the code snippets with SQL type if necessary.",
-                                                                                       "Do not include any links or external references in your response.\n",
-                                                                                       "Do not change the code snippets.\n",
-                                                                                       "Do not change the SQL syntax, please don't make up the function.\n",
-                                                                                       "Do not change explain any code snippets.\n",
-                                                                                       "Make the whole answer as short as possible to keep the code snippets.\n"
-        ]);
-        let mut input_variables = HashMap::new();
-        input_variables.insert("question", question);
-        input_variables.insert("contexts", &contexts);
-        let prompt = prompt_template.format(input_variables)?;
-
-        info!("prompt: {}", prompt);
-
-        let databend_llm = DatabendLLM::create(&dsn);
-        let result = databend_llm.generate(&prompt).await?;
+            .map(|x| x.as_str())
+            .collect::<Vec<_>>();
+        let prompt_template = DocumentRetrievalPrompt::create().with_instructions(instructions);
+        let format_prompt = |contexts: &str| {
+            let mut input_variables = HashMap::new();
+            input_variables.insert("question", question);
+            input_variables.insert("contexts", contexts);
+            prompt_template.format(input_variables)
+        };
+
+        // The contexts get the tokens left by the instructions and the question.
+        let builder = QAContextBuilder::create(self.llm.clone(), self.conf.qa.context_max_tokens)?
+            .with_overflow(QAContextOverflow::from_str(&self.conf.qa.context_overflow)?);
+        let fixed_tokens = builder.tokens(&format_prompt("")?);
+        let budget = match self.conf.qa.prompt_max_tokens.checked_sub(fixed_tokens) {
+            Some(left) => left.min(self.conf.qa.context_max_tokens),
+            None => bail!(
+                "prompt without contexts takes {} tokens, over prompt_max_tokens {}",
+                fixed_tokens,
+                self.conf.qa.prompt_max_tokens
+            ),
+        };
+        let builder = builder.with_max_tokens(budget);
+        let mut context = builder.build(chunks, |x| self.format(x)).await?;
+        if self.conf.qa.context_parent_section && context.tokens < budget {
+            match self.document_chunks(&context.chunks, true).await {
+                Ok(sections) => {
+                    builder.expand_sections(&mut context, &sections, |x| self.format(x))
+                }
+                Err(e) => warn!("Failed to fetch the parent sections: {:?}", e),
+            }
+        }
+        if self.conf.qa.context_neighbors > 0 && context.tokens < budget {
+            match self.document_chunks(&context.chunks, false).await {
+                Ok(neighbors) => builder.expand(&mut context, &neighbors, |x| self.format(x)),
+                Err(e) => warn!("Failed to fetch the neighbor chunks: {:?}", e),
+            }
+        }
+        let contexts = context.text();
+        explain.timings.context_ms = now.elapsed().as_millis() as u64;
+
+        let prompt = format_prompt(&contexts)?;
+
+        debug!("prompt: {}", prompt);
+        explain.tokens = QAExplainTokens {
+            prompt: builder.tokens(&prompt),
+            context: context.tokens,
+            shortened_chunks: context.shortened,
+            dropped_chunks: context.dropped,
+            expanded_chunks: context.expanded,
+            ..Default::default()
+        };
+        info!(
+            "prompt tokens:{}/{}, context tokens:{}/{}, chunks:{}, shortened:{}, dropped:{}, expanded:{}",
+            explain.tokens.prompt,
+            self.conf.qa.prompt_max_tokens,
+            context.tokens,
+            budget,
+            context.chunks.len(),
+            context.shortened,
+            context.dropped,
+            context.expanded
+        );
+
+        let now = Instant::now();
+        let result = self.llm.generate(&prompt).await?;
+        explain.timings.generate_ms = now.elapsed().as_millis() as u64;
+        explain.tokens.completion = builder.tokens(&result.generation);
+
+        if !self.conf.qa.answer_table.is_empty() {
+            let similarities = context
+                .chunks
+                .iter()
+                .map(|x| x.similarity)
+                .collect::<Vec<_>>();
+            let rewrites = &explain.queries[1..];
+            let record = match QADatabase::connect(&self.conf).await {
+                Ok(db) => {
+                    db.insert_answer(
+                        question,
+                        &prompt,
+                        &similarities,
+                        &contexts,
+                        &result.generation,
+                        rewrites,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = record {
+                warn!("Failed to insert the answer record: {:?}", e);
+            }
+        }
 
+        explain.prompt = prompt;
+        explain.chunks = context.chunks;
         Ok(result.generation)
     }
+
+    /// The parent section chunks or the adjacent chunks of the chunks, from the table of their collection.
+    async fn document_chunks(&self, chunks: &[QAChunk], sections: bool) -> Result<Vec<QAChunk>> {
+        let mut collections: Vec<&str> = vec![];
+        for chunk in chunks {
+            if !collections.contains(&chunk.collection.as_str()) {
+                collections.push(&chunk.collection);
+            }
+        }
+
+        let fetches = collections.into_iter().map(|name| async move {
+            let conf = self.conf.with_collection(name)?;
+            let hits = chunks
+                .iter()
+                .filter(|x| x.collection == name)
+                .cloned()
+                .collect::<Vec<_>>();
+            let vector_store = QAVectorStore::create(&conf);
+            let mut related = if sections {
+                vector_store.sections(&hits).await?
+            } else {
+                vector_store
+                    .neighbors(&hits, self.conf.qa.context_neighbors)
+                    .await?
+            };
+            for chunk in related.iter_mut() {
+                chunk.collection = name.to_string();
+            }
+            Ok::<_, anyhow::Error>(related)
+        });
+        Ok(try_join_all(fetches).await?.into_iter().flatten().collect())
+    }
+
+    /// The context of the chunk, with the page url of its collection.
+    fn format(&self, chunk: &QAChunk) -> String {
+        let base_url = self.conf.qa.collection_base_url(&chunk.collection);
+        Self::format_context(chunk, &base_url)
+    }
+
+    /// The context of the chunk with its collection, page title and section.
+    pub fn format_context(chunk: &QAChunk, base_url: &str) -> String {
+        let mut context = String::new();
+        if !chunk.collection.is_empty() {
+            context.push_str(&format!("collection:{}\n", chunk.collection));
+        }
+        if !chunk.title.is_empty() {
+            context.push_str(&format!("title:{}\n", chunk.title));
+        }
+        if !chunk.headings.is_empty() {
+            context.push_str(&format!("section:{}\n", chunk.headings));
+        }
+        context.push_str(&format!(
+            "context:{}\nsource:{}\n",
+            chunk.content,
+            chunk.source(base_url)
+        ));
+        context
+    }
 }
@@ -0,0 +1,294 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use glob::Pattern;
+use llmchain::DirectoryLoader;
+use llmchain::Disk;
+use llmchain::Document;
+use llmchain::DocumentLoader;
+use llmchain::DocumentPath;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+use llmchain::LocalDisk;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::configs::QAConfig;
+use crate::qa::QAChunk;
+use crate::qa::QACodeSplitter;
+use crate::qa::QAMarkdownSplitter;
+use crate::qa::QATextSplitter;
+
+/// Built-in loaders of the files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QALoaderKind {
+    Markdown,
+    /// Markdown with JSX, the imports, exports and JSX tags are stripped.
+    Mdx,
+    /// Flattened to `key.path: value` lines.
+    Json,
+    /// Source code split by the function or type definitions.
+    Code,
+    Text,
+}
+
+impl FromStr for QALoaderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" => Ok(QALoaderKind::Markdown),
+            "mdx" => Ok(QALoaderKind::Mdx),
+            "json" => Ok(QALoaderKind::Json),
+            "code" => Ok(QALoaderKind::Code),
+            "text" => Ok(QALoaderKind::Text),
+            _ => Err(anyhow!(
+                "unknown loader: {}, expect markdown|mdx|json|code|text",
+                s
+            )),
+        }
+    }
+}
+
+impl QALoaderKind {
+    /// Convert the file content to the text to embed.
+    pub fn transform(&self, content: &str) -> String {
+        match self {
+            QALoaderKind::Mdx => Self::strip_jsx(content),
+            QALoaderKind::Json => {
+                Self::flatten_json(content).unwrap_or_else(|| content.to_string())
+            }
+            _ => content.to_string(),
+        }
+    }
+
+    fn strip_jsx(content: &str) -> String {
+        let tag = Regex::new(r"</?[A-Z][\w.]*(\s[^<>]*)?/?>").unwrap();
+        let open_tag = Regex::new(r"^\s*</?[A-Z][\w.]*(\s[^<>]*)?$").unwrap();
+        let comment = Regex::new(r"\{/\*.*?\*/\}").unwrap();
+
+        let mut lines = vec![];
+        let mut in_code = false;
+        let mut in_tag = false;
+        for line in content.lines() {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+                lines.push(line.to_string());
+                continue;
+            }
+            if in_code {
+                lines.push(line.to_string());
+                continue;
+            }
+            // The props of a JSX tag across lines.
+            if in_tag {
+                in_tag = !line.contains('>');
+                continue;
+            }
+            if open_tag.is_match(line) {
+                in_tag = true;
+                continue;
+            }
+            if line.starts_with("import ") || line.starts_with("export ") {
+                continue;
+            }
+            let stripped = comment.replace_all(line, "");
+            let stripped = tag.replace_all(&stripped, "");
+            // Drop the lines of the JSX only.
+            if stripped.trim().is_empty() && !line.trim().is_empty() {
+                continue;
+            }
+            lines.push(stripped.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn flatten_json(content: &str) -> Option<String> {
+        fn flatten(prefix: &str, value: &Value, lines: &mut Vec<String>) {
+            match value {
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        let key = if prefix.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{}.{}", prefix, key)
+                        };
+                        flatten(&key, value, lines);
+                    }
+                }
+                Value::Array(values) => {
+                    for (i, value) in values.iter().enumerate() {
+                        flatten(&format!("{}[{}]", prefix, i), value, lines);
+                    }
+                }
+                Value::Null => {}
+                Value::String(s) => lines.push(format!("{}: {}", prefix, s)),
+                _ => lines.push(format!("{}: {}", prefix, value)),
+            }
+        }
+
+        let value: Value = serde_json::from_str(content).ok()?;
+        let mut lines = vec![];
+        flatten("", &value, &mut lines);
+        Some(lines.join("\n"))
+    }
+}
+
+/// Loader of the docs, dispatches the files to the loaders by `qa.loaders`.
+pub struct QALoader {
+    disk: Arc<LocalDisk>,
+    loaders: Vec<(Pattern, QALoaderKind)>,
+    ignored_paths: Vec<Pattern>,
+    markdown_splitter: QAMarkdownSplitter,
+    code_splitter: QACodeSplitter,
+    text_splitter: QATextSplitter,
+    skipped_files: Mutex<Vec<String>>,
+}
+
+impl QALoader {
+    pub fn create(conf: &QAConfig) -> Result<Arc<Self>> {
+        let loaders = conf
+            .loaders
+            .iter()
+            .map(|x| Ok((Pattern::new(&x.glob)?, QALoaderKind::from_str(&x.loader)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let ignored_paths = conf
+            .ignored_paths
+            .iter()
+            .map(|x| Pattern::new(x))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if conf.chunk_overlap >= conf.chunk_size {
+            return Err(anyhow!(
+                "chunk_overlap {} must be less than chunk_size {}",
+                conf.chunk_overlap,
+                conf.chunk_size
+            ));
+        }
+        let markdown_splitter = QAMarkdownSplitter::create()
+            .with_chunk_size(conf.chunk_size)
+            .with_chunk_overlap(conf.chunk_overlap)
+            .with_min_chunk_size(conf.min_chunk_size)
+            .with_split_code_blocks(conf.split_code_blocks)
+            .with_prepend_headings(conf.prepend_headings);
+        let code_splitter = QACodeSplitter::create().with_chunk_size(conf.chunk_size);
+        let text_splitter = QATextSplitter::create()
+            .with_chunk_size(conf.chunk_size)
+            .with_chunk_overlap(conf.chunk_overlap);
+
+        Ok(Arc::new(QALoader {
+            disk: LocalDisk::create()?,
+            loaders,
+            ignored_paths,
+            markdown_splitter,
+            code_splitter,
+            text_splitter,
+            skipped_files: Mutex::new(vec![]),
+        }))
+    }
+
+    /// The loader of the file, None if it's ignored or no loader matches.
+    pub fn kind(&self, path: &str) -> Option<QALoaderKind> {
+        if self.ignored_paths.iter().any(|x| x.matches(path)) {
+            return None;
+        }
+        self.loaders
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, kind)| *kind)
+    }
+
+    /// The files matching a loader but skipped by `ignored_paths`.
+    pub fn skipped_files(&self) -> Vec<String> {
+        self.skipped_files.lock().unwrap().clone()
+    }
+
+    /// Load the files of the directory matching the loader globs.
+    pub async fn load_directory(self: &Arc<Self>, path: &str) -> Result<Documents> {
+        let mut loader = DirectoryLoader::create(self.disk.clone());
+        for (pattern, _) in &self.loaders {
+            loader = loader.with_loader(pattern.as_str(), self.clone());
+        }
+        loader.load(DocumentPath::Str(path.to_string())).await
+    }
+
+    /// Load the files still exist, the deleted ones are skipped.
+    pub async fn load_files(&self, files: &[PathBuf]) -> Result<Documents> {
+        let documents = Documents::create();
+        for file in files {
+            if file.is_file() {
+                let path = DocumentPath::Str(file.display().to_string());
+                documents.extend(&self.load(path).await?);
+            }
+        }
+        Ok(documents)
+    }
+
+    /// Split the documents by the splitter of their loaders.
+    /// The markdown pages are split by their headings, with the front matter as the chunk metadata.
+    pub fn split_documents(&self, documents: &Documents) -> Result<Vec<QAChunk>> {
+        let mut chunks = vec![];
+        for document in documents {
+            let kind = self.kind(&document.path).unwrap_or(QALoaderKind::Text);
+            let mut splitted = match kind {
+                QALoaderKind::Markdown | QALoaderKind::Mdx => self
+                    .markdown_splitter
+                    .split(&document.path, &document.content),
+                QALoaderKind::Code => Self::split_by(&self.code_splitter, document)?,
+                QALoaderKind::Json | QALoaderKind::Text => {
+                    Self::split_by(&self.text_splitter, document)?
+                }
+            };
+            for (i, chunk) in splitted.iter_mut().enumerate() {
+                chunk.index = i;
+            }
+            chunks.extend(splitted);
+        }
+        Ok(chunks)
+    }
+
+    fn split_by(splitter: &dyn DocumentSplitter, document: Document) -> Result<Vec<QAChunk>> {
+        Ok(splitter
+            .split_documents(&Documents::from(vec![document]))?
+            .iter()
+            .map(|x| QAChunk::create(&x.path, &x.content))
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentLoader for QALoader {
+    async fn load(&self, path: DocumentPath) -> Result<Documents> {
+        let path = path.as_str()?;
+        let documents = Documents::create();
+        let kind = match self.kind(path) {
+            Some(kind) => kind,
+            None => {
+                self.skipped_files.lock().unwrap().push(path.to_string());
+                return Ok(documents);
+            }
+        };
+
+        let bs = self.disk.get_operator()?.read(path).await?;
+        let content = kind.transform(&String::from_utf8_lossy(&bs));
+        documents.push(Document::create(path, &content));
+        Ok(documents)
+    }
+}
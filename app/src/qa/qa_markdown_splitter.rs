@@ -0,0 +1,244 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use regex::Regex;
+
+use crate::qa::QAChunk;
+use crate::qa::QAFrontMatter;
+
+/// Splitter of the markdown pages.
+/// The front matter is stripped as the page metadata, the chunks never cross a heading
+/// and carry the heading path of their section.
+pub struct QAMarkdownSplitter {
+    pub splitter_chunk_size: usize,
+    /// Trailing size of a chunk repeated at the start of the next chunk of the section.
+    pub chunk_overlap: usize,
+    /// Chunks smaller than this are merged into their neighbour chunk of the page.
+    pub min_chunk_size: usize,
+    /// Split the code blocks larger than the chunk size, they are kept whole by default.
+    pub split_code_blocks: bool,
+    /// Prepend the `title > headings` path to the chunk content.
+    pub prepend_headings: bool,
+}
+
+impl QAMarkdownSplitter {
+    pub fn create() -> Self {
+        QAMarkdownSplitter {
+            splitter_chunk_size: 400,
+            chunk_overlap: 0,
+            min_chunk_size: 0,
+            split_code_blocks: false,
+            prepend_headings: false,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.splitter_chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_chunk_overlap(mut self, chunk_overlap: usize) -> Self {
+        self.chunk_overlap = chunk_overlap;
+        self
+    }
+
+    pub fn with_min_chunk_size(mut self, min_chunk_size: usize) -> Self {
+        self.min_chunk_size = min_chunk_size;
+        self
+    }
+
+    pub fn with_split_code_blocks(mut self, split_code_blocks: bool) -> Self {
+        self.split_code_blocks = split_code_blocks;
+        self
+    }
+
+    pub fn with_prepend_headings(mut self, prepend_headings: bool) -> Self {
+        self.prepend_headings = prepend_headings;
+        self
+    }
+
+    pub fn split(&self, path: &str, content: &str) -> Vec<QAChunk> {
+        let (front_matter, body) = QAFrontMatter::parse(content);
+        let sections = Self::sections(body);
+
+        // Fall back to the first heading if the page has no title.
+        let title = match front_matter.page_title() {
+            "" => sections
+                .iter()
+                .find_map(|(headings, _)| headings.first().cloned())
+                .unwrap_or_default(),
+            title => title.to_string(),
+        };
+
+        let mut chunks: Vec<QAChunk> = vec![];
+        for (section_id, (headings, text)) in sections.into_iter().enumerate() {
+            for content in self.split_text(&text) {
+                chunks.push(QAChunk {
+                    path: path.to_string(),
+                    content,
+                    title: title.clone(),
+                    slug: front_matter.slug.clone(),
+                    version: front_matter.version.clone(),
+                    tags: front_matter.tags.clone(),
+                    headings: headings.join(" > "),
+                    section_id,
+                    ..Default::default()
+                });
+            }
+        }
+
+        let mut chunks = self.merge_small_chunks(chunks);
+        if self.prepend_headings {
+            for chunk in chunks.iter_mut() {
+                let path = [chunk.title.as_str(), chunk.headings.as_str()]
+                    .into_iter()
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" > ");
+                if !path.is_empty() {
+                    chunk.content = format!("{}\n\n{}", path, chunk.content);
+                }
+            }
+        }
+        chunks
+    }
+
+    /// Split the body by the headings, return the heading path and the text of each section.
+    fn sections(body: &str) -> Vec<(Vec<String>, String)> {
+        let heading = Regex::new(r"^(#{1,6})\s+(.+?)[\s#]*$").unwrap();
+
+        let mut sections = vec![];
+        let mut stack: Vec<(usize, String)> = vec![];
+        let mut lines: Vec<&str> = vec![];
+        let mut in_code = false;
+        for line in body.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code = !in_code;
+            } else if !in_code {
+                if let Some(caps) = heading.captures(line) {
+                    let headings = stack.iter().map(|(_, x)| x.clone()).collect();
+                    sections.push((headings, lines.join("\n")));
+                    lines.clear();
+
+                    let level = caps[1].len();
+                    stack.retain(|(x, _)| *x < level);
+                    stack.push((level, caps[2].to_string()));
+                }
+            }
+            lines.push(line);
+        }
+        let headings = stack.iter().map(|(_, x)| x.clone()).collect();
+        sections.push((headings, lines.join("\n")));
+
+        sections.retain(|(_, text)| !text.trim().is_empty());
+        sections
+    }
+
+    /// Split the section text by lines, each chunk is at most the chunk size unless a line,
+    /// or a code block kept whole, is larger.
+    fn split_text(&self, text: &str) -> Vec<String> {
+        let mut chunks = vec![];
+        let mut chunk: Vec<String> = vec![];
+        for unit in self.units(text) {
+            let size = chunk.iter().map(|x| x.len() + 1).sum::<usize>();
+            if !chunk.is_empty() && size + unit.len() >= self.splitter_chunk_size {
+                let overlap = self.overlap(&chunk);
+                chunks.push(chunk.join("\n"));
+                chunk = overlap;
+
+                // Drop the overlap if the chunk can't hold it together with the unit.
+                let size = chunk.iter().map(|x| x.len() + 1).sum::<usize>();
+                if size + unit.len() >= self.splitter_chunk_size {
+                    chunk.clear();
+                }
+            }
+            chunk.push(unit);
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk.join("\n"));
+        }
+        chunks.retain(|x| !x.trim().is_empty());
+        chunks
+    }
+
+    /// The lines of the text, a code block is a single unit unless `split_code_blocks` is set.
+    fn units(&self, text: &str) -> Vec<String> {
+        let mut units = vec![];
+        let mut code: Option<Vec<&str>> = None;
+        for line in text.trim().lines() {
+            let trimmed = line.trim_start();
+            let is_fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+            match code.as_mut() {
+                Some(block) => {
+                    block.push(line);
+                    if is_fence {
+                        units.push(block.join("\n"));
+                        code = None;
+                    }
+                }
+                None if is_fence && !self.split_code_blocks => code = Some(vec![line]),
+                None => units.push(line.to_string()),
+            }
+        }
+        // Unclosed code block.
+        if let Some(block) = code {
+            units.push(block.join("\n"));
+        }
+        units
+    }
+
+    /// The trailing units of the chunk within the overlap size.
+    fn overlap(&self, chunk: &[String]) -> Vec<String> {
+        let mut size = 0;
+        let mut overlap = vec![];
+        for unit in chunk.iter().rev() {
+            size += unit.len() + 1;
+            if size > self.chunk_overlap {
+                break;
+            }
+            overlap.push(unit.clone());
+        }
+        overlap.reverse();
+        overlap
+    }
+
+    /// Merge the chunks smaller than the min chunk size into the next chunk of the page,
+    /// or the previous one for the last chunk.
+    fn merge_small_chunks(&self, chunks: Vec<QAChunk>) -> Vec<QAChunk> {
+        if self.min_chunk_size == 0 {
+            return chunks;
+        }
+
+        let mut merged: Vec<QAChunk> = vec![];
+        let mut pending: Option<QAChunk> = None;
+        for mut chunk in chunks {
+            if let Some(small) = pending.take() {
+                chunk.content = format!("{}\n\n{}", small.content, chunk.content);
+            }
+            if chunk.content.len() < self.min_chunk_size {
+                pending = Some(chunk);
+            } else {
+                merged.push(chunk);
+            }
+        }
+        if let Some(small) = pending {
+            match merged.last_mut() {
+                Some(last) => last.content = format!("{}\n\n{}", last.content, small.content),
+                None => merged.push(small),
+            }
+        }
+        merged
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use llmchain::LLM;
+use log::info;
+use log::warn;
+
+use crate::qa::QAChunk;
+
+/// Rewrites the question into the search queries before the retrieval.
+pub struct QAQueryRewriter {
+    llm: Arc<dyn LLM>,
+    max_queries: usize,
+}
+
+impl QAQueryRewriter {
+    pub fn create(llm: Arc<dyn LLM>) -> Self {
+        QAQueryRewriter {
+            llm,
+            max_queries: 3,
+        }
+    }
+
+    pub fn with_max_queries(mut self, max_queries: usize) -> Self {
+        self.max_queries = max_queries;
+        self
+    }
+
+    /// The question followed by its rewrites, only the question if the LLM fails.
+    pub async fn rewrite(&self, question: &str) -> Vec<String> {
+        match self.llm.generate(&self.prompt(question)).await {
+            Ok(result) => {
+                let queries = self.parse_queries(question, &result.generation);
+                info!("rewrite {:?} to {:?}", question, queries);
+                queries
+            }
+            Err(e) => {
+                warn!("Failed to rewrite {:?}: {:?}", question, e);
+                vec![question.to_string()]
+            }
+        }
+    }
+
+    fn prompt(&self, question: &str) -> String {
+        format!(
+            "You help to search the Databend documentation.\n\
+             Rewrite the question into at most {} short search queries phrased like the documentation, \
+             fix the vague words and expand the abbreviations.\n\
+             Then extract the SQL keywords, functions and object names the answer may use.\n\
+             Reply with one query per line starting with `- `, and a last line `keywords: ` \
+             followed by the comma separated keywords, nothing else.\n\n\
+             Question: {}\n",
+            self.max_queries, question
+        )
+    }
+
+    /// Parse the queries and keywords of the generation, the question is always the first query.
+    pub fn parse_queries(&self, question: &str, generation: &str) -> Vec<String> {
+        let mut queries = vec![question.trim().to_string()];
+        let mut rewrites = 0;
+        for line in generation.lines() {
+            let line = line.trim();
+            let query = match line.strip_prefix("keywords:") {
+                Some(keywords) => keywords
+                    .split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                None if rewrites < self.max_queries => {
+                    let query = line
+                        .trim_start_matches(|c: char| {
+                            c == '-' || c == '*' || c == '.' || c.is_ascii_digit()
+                        })
+                        .trim();
+                    if !query.is_empty() {
+                        rewrites += 1;
+                    }
+                    query.to_string()
+                }
+                None => continue,
+            };
+            if !query.is_empty() && !queries.iter().any(|x| x.eq_ignore_ascii_case(&query)) {
+                queries.push(query);
+            }
+        }
+        queries
+    }
+
+    /// Merge the chunks retrieved for the queries, each chunk is kept once with its best score.
+    pub fn merge(results: Vec<Vec<QAChunk>>, top: usize) -> Vec<QAChunk> {
+        let mut chunks: Vec<QAChunk> = vec![];
+        let mut seen = HashSet::new();
+        let mut all = results.into_iter().flatten().collect::<Vec<_>>();
+        // The stable sort keeps the query order for the same score.
+        all.sort_by(|a, b| b.score.total_cmp(&a.score));
+        for chunk in all {
+            let key = (
+                chunk.collection.clone(),
+                chunk.path.clone(),
+                chunk.content.clone(),
+            );
+            if seen.insert(key) {
+                chunks.push(chunk);
+            }
+        }
+        chunks.truncate(top);
+        chunks
+    }
+}
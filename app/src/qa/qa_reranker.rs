@@ -0,0 +1,235 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::LLM;
+use log::debug;
+use log::warn;
+use regex::Regex;
+
+use crate::qa::QAChunk;
+use crate::Config;
+
+/// Words ignored by the lexical reranker.
+const STOP_WORDS: [&str; 32] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "in", "is", "it", "of", "on", "or", "the", "this", "to", "use", "what", "whats", "when",
+    "where", "which", "with", "you",
+];
+
+/// Max chars of a chunk in the LLM rerank prompt.
+const LLM_RERANK_CHUNK_CHARS: usize = 1000;
+
+/// Scores the candidates of the vector search, the higher the more relevant.
+#[async_trait::async_trait]
+pub trait QAReranker: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// The score of each chunk, in the order of the chunks.
+    async fn scores(&self, question: &str, chunks: &[QAChunk]) -> Result<Vec<f32>>;
+
+    /// Rerank the candidates and keep the top ones, the ties keep the vector search order.
+    /// The top ones of the vector search are kept if the scoring fails.
+    async fn rerank(
+        &self,
+        question: &str,
+        mut chunks: Vec<QAChunk>,
+        top: usize,
+    ) -> Result<Vec<QAChunk>> {
+        let scores = self.scores(question, &chunks).await.and_then(|scores| {
+            if scores.len() != chunks.len() {
+                return Err(anyhow!(
+                    "reranker {} returns {} scores for {} chunks",
+                    self.name(),
+                    scores.len(),
+                    chunks.len()
+                ));
+            }
+            Ok(scores)
+        });
+        let scores = match scores {
+            Ok(scores) => scores,
+            Err(e) => {
+                warn!(
+                    "Failed to rerank {:?} by {}, keep the vector search order: {:?}",
+                    question,
+                    self.name(),
+                    e
+                );
+                chunks.truncate(top);
+                return Ok(chunks);
+            }
+        };
+        for (chunk, score) in chunks.iter_mut().zip(scores) {
+            chunk.rerank_score = score;
+        }
+        chunks.sort_by(|a, b| b.rerank_score.total_cmp(&a.rerank_score));
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            debug!(
+                "rerank by {}: #{} {} [{}], similarity:{}, rerank score:{}{}",
+                self.name(),
+                i + 1,
+                chunk.path,
+                chunk.headings,
+                chunk.similarity,
+                chunk.rerank_score,
+                if i < top { "" } else { ", dropped" }
+            );
+        }
+        chunks.truncate(top);
+        Ok(chunks)
+    }
+}
+
+/// Built-in rerankers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QARerankerKind {
+    Off,
+    /// Fraction of the question words found in the chunk.
+    Lexical,
+    /// Relevance rated by the LLM.
+    LLM,
+}
+
+impl FromStr for QARerankerKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "off" | "" => Ok(QARerankerKind::Off),
+            "lexical" => Ok(QARerankerKind::Lexical),
+            "llm" => Ok(QARerankerKind::LLM),
+            _ => Err(anyhow!("unknown reranker: {}, expect off|lexical|llm", s)),
+        }
+    }
+}
+
+impl QARerankerKind {
+    /// The reranker of the config, None if it's off.
+    pub fn create(conf: &Config, llm: Arc<dyn LLM>) -> Result<Option<Arc<dyn QAReranker>>> {
+        let reranker: Arc<dyn QAReranker> = match Self::from_str(&conf.qa.rerank)? {
+            QARerankerKind::Off => return Ok(None),
+            QARerankerKind::Lexical => Arc::new(QALexicalReranker::create()),
+            QARerankerKind::LLM => Arc::new(QALLMReranker::create(llm)),
+        };
+        Ok(Some(reranker))
+    }
+}
+
+pub struct QALexicalReranker {}
+
+impl QALexicalReranker {
+    pub fn create() -> Self {
+        QALexicalReranker {}
+    }
+
+    fn words(text: &str) -> HashSet<String> {
+        text.to_lowercase()
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|x| !x.is_empty() && !STOP_WORDS.contains(x))
+            .map(|x| x.to_string())
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl QAReranker for QALexicalReranker {
+    fn name(&self) -> &str {
+        "lexical"
+    }
+
+    async fn scores(&self, question: &str, chunks: &[QAChunk]) -> Result<Vec<f32>> {
+        let terms = Self::words(question);
+        if terms.is_empty() {
+            return Ok(vec![0.0; chunks.len()]);
+        }
+
+        Ok(chunks
+            .iter()
+            .map(|chunk| {
+                let words = Self::words(&format!(
+                    "{} {} {}",
+                    chunk.title, chunk.headings, chunk.content
+                ));
+                terms.iter().filter(|x| words.contains(*x)).count() as f32 / terms.len() as f32
+            })
+            .collect())
+    }
+}
+
+pub struct QALLMReranker {
+    llm: Arc<dyn LLM>,
+}
+
+impl QALLMReranker {
+    pub fn create(llm: Arc<dyn LLM>) -> Self {
+        QALLMReranker { llm }
+    }
+
+    fn prompt(question: &str, chunks: &[QAChunk]) -> String {
+        let passages = chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let content = match chunk.content.char_indices().nth(LLM_RERANK_CHUNK_CHARS) {
+                    Some((end, _)) => &chunk.content[..end],
+                    None => &chunk.content,
+                };
+                format!("[{}] {}\n{}", i + 1, chunk.headings, content)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        format!(
+            "Rate how well each passage helps to answer the question, from 0 (irrelevant) to 10 (answers it).\n\
+             Reply with one line per passage formatted as `[number]: score`, nothing else.\n\n\
+             Question: {}\n\nPassages:\n{}\n",
+            question, passages
+        )
+    }
+
+    /// Parse the `[number]: score` lines, the passages not rated score 0.
+    pub fn parse_scores(generation: &str, n: usize) -> Vec<f32> {
+        let line = Regex::new(r"^\s*\[?(\d+)\]?\s*[:=\-]\s*(\d+(?:\.\d+)?)").unwrap();
+        let mut scores = vec![0.0; n];
+        for caps in generation.lines().filter_map(|x| line.captures(x)) {
+            let i = caps[1].parse::<usize>().unwrap_or_default();
+            if i >= 1 && i <= n {
+                scores[i - 1] = caps[2].parse::<f32>().unwrap_or_default().clamp(0.0, 10.0) / 10.0;
+            }
+        }
+        scores
+    }
+}
+
+#[async_trait::async_trait]
+impl QAReranker for QALLMReranker {
+    fn name(&self) -> &str {
+        "llm"
+    }
+
+    async fn scores(&self, question: &str, chunks: &[QAChunk]) -> Result<Vec<f32>> {
+        if chunks.is_empty() {
+            return Ok(vec![]);
+        }
+        let result = self.llm.generate(&Self::prompt(question, chunks)).await?;
+        debug!("llm rerank scores: {:?}", result.generation);
+        Ok(Self::parse_scores(&result.generation, chunks.len()))
+    }
+}
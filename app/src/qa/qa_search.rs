@@ -0,0 +1,221 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::anyhow;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::base::escape_sql_like;
+use crate::base::escape_sql_string;
+use crate::qa::QAChunk;
+use crate::qa::QAFederatedSearch;
+use crate::qa::QAVectorStore;
+use crate::Config;
+
+/// Metadata filters of the chunks, pushed down into the vector search.
+/// The chunks embedded before their metadata only match the empty filters.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QASearchFilter {
+    /// Part of the document path, eg: `12-load-data/`.
+    pub path: String,
+    /// Start of the document path under the source root, eg: `12-load-data/02-stage`.
+    pub path_prefix: String,
+    /// Part of the heading path, case insensitive, eg: `copy into`.
+    pub heading: String,
+    /// Top directories of the documents to search, any of them, eg: `02-cloud`.
+    pub sections: Vec<String>,
+    /// Top directories of the documents not to search, eg: `10-deploy`.
+    pub exclude_sections: Vec<String>,
+    /// Product version of the pages.
+    pub version: String,
+    /// Tags the pages have, all of them.
+    pub tags: Vec<String>,
+}
+
+impl QASearchFilter {
+    /// The SQL conditions of the filter, each one starts with ` AND `.
+    pub fn conditions(&self) -> String {
+        let mut conditions = String::new();
+        if !self.path.is_empty() {
+            conditions.push_str(&format!(
+                " AND path LIKE '%{}%'",
+                escape_sql_string(&escape_sql_like(&self.path))
+            ));
+        }
+        if !self.path_prefix.is_empty() {
+            conditions.push_str(&format!(
+                " AND doc_path LIKE '{}%'",
+                escape_sql_string(&escape_sql_like(self.path_prefix.trim_start_matches('/')))
+            ));
+        }
+        if !self.heading.is_empty() {
+            conditions.push_str(&format!(
+                " AND lower(headings) LIKE '%{}%'",
+                escape_sql_string(&escape_sql_like(&self.heading.to_lowercase()))
+            ));
+        }
+        if !self.sections.is_empty() {
+            conditions.push_str(&format!(
+                " AND doc_section IN ({})",
+                Self::values(&self.sections)
+            ));
+        }
+        if !self.exclude_sections.is_empty() {
+            // The chunks without a section are kept.
+            conditions.push_str(&format!(
+                " AND (doc_section IS NULL OR doc_section NOT IN ({}))",
+                Self::values(&self.exclude_sections)
+            ));
+        }
+        if !self.version.is_empty() {
+            conditions.push_str(&format!(
+                " AND version = '{}'",
+                escape_sql_string(&self.version)
+            ));
+        }
+        for tag in &self.tags {
+            conditions.push_str(&format!(
+                " AND concat(',', tags, ',') LIKE '%,{},%'",
+                escape_sql_string(&escape_sql_like(tag))
+            ));
+        }
+        conditions
+    }
+
+    fn values(values: &[String]) -> String {
+        values
+            .iter()
+            .map(|x| format!("'{}'", escape_sql_string(x)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A chunk of the search results.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QASearchHit {
+    /// Collection of the chunk, only set if several collections are searched.
+    pub collection: String,
+    pub path: String,
+    /// The page url of the chunk, its path if the page has no url.
+    pub source: String,
+    pub title: String,
+    pub headings: String,
+    pub content: String,
+    pub similarity: f32,
+    /// The similarity, weighted by the collection if several collections are searched.
+    pub score: f32,
+}
+
+impl QASearchHit {
+    pub fn create(chunk: QAChunk, base_url: &str) -> Self {
+        QASearchHit {
+            source: chunk.source(base_url),
+            collection: chunk.collection,
+            path: chunk.path,
+            title: chunk.title,
+            headings: chunk.headings,
+            content: chunk.content,
+            similarity: chunk.similarity,
+            score: chunk.score,
+        }
+    }
+}
+
+/// A page of the search results, best first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QASearchPage {
+    pub page: usize,
+    pub page_size: usize,
+    pub hits: Vec<QASearchHit>,
+    /// There are hits after this page.
+    pub has_more: bool,
+}
+
+/// Retrieval only search of the ranked chunks, no answer is generated.
+pub struct QASearch {
+    conf: Config,
+    filter: QASearchFilter,
+}
+
+impl QASearch {
+    pub fn create(conf: &Config) -> Self {
+        QASearch {
+            conf: conf.clone(),
+            filter: QASearchFilter::default(),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: &QASearchFilter) -> Self {
+        self.filter = filter.clone();
+        self
+    }
+
+    /// The page of the chunks most similar to the query, the pages start from 1.
+    /// The collections are searched together if set.
+    pub async fn search(
+        &self,
+        query: &str,
+        collections: Option<&[String]>,
+        page: usize,
+        page_size: usize,
+    ) -> Result<QASearchPage> {
+        let page = page.max(1);
+        let offset = (page - 1)
+            .checked_mul(page_size)
+            .filter(|x| x.checked_add(page_size + 1).is_some())
+            .ok_or_else(|| anyhow!("page {} is too large", page))?;
+        // One more chunk than the page tells if there is a next page.
+        let mut chunks = match collections {
+            None => {
+                let mut chunks = QAVectorStore::create(&self.conf)
+                    .filtered_search(query, &self.filter, offset, page_size + 1)
+                    .await?;
+                for chunk in chunks.iter_mut() {
+                    chunk.score = chunk.similarity;
+                }
+                chunks
+            }
+            Some(collections) => {
+                // The merged ranking needs the chunks of the previous pages of each collection.
+                QAFederatedSearch::create(&self.conf)
+                    .with_top(offset + page_size + 1)
+                    .with_filter(&self.filter)
+                    .search(query, collections)
+                    .await?
+                    .into_iter()
+                    .skip(offset)
+                    .collect()
+            }
+        };
+        let has_more = chunks.len() > page_size;
+        chunks.truncate(page_size);
+
+        let hits = chunks
+            .into_iter()
+            .map(|x| {
+                let base_url = self.conf.qa.collection_base_url(&x.collection);
+                QASearchHit::create(x, &base_url)
+            })
+            .collect();
+        Ok(QASearchPage {
+            page,
+            page_size,
+            hits,
+            has_more,
+        })
+    }
+}
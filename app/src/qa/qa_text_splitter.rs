@@ -0,0 +1,103 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::Result;
+use llmchain::Document;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+
+/// Splitter of the plain text, the chunks break at the lines.
+/// The trailing lines of a chunk within the overlap size are repeated at the start of the next one.
+pub struct QATextSplitter {
+    pub splitter_chunk_size: usize,
+    pub chunk_overlap: usize,
+}
+
+impl QATextSplitter {
+    pub fn create() -> Self {
+        QATextSplitter {
+            splitter_chunk_size: 400,
+            chunk_overlap: 0,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.splitter_chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_chunk_overlap(mut self, chunk_overlap: usize) -> Self {
+        self.chunk_overlap = chunk_overlap;
+        self
+    }
+
+    /// Split the text by lines, a line larger than the chunk size is kept whole.
+    pub fn split_text(&self, text: &str) -> Vec<String> {
+        let mut chunks = vec![];
+        let mut chunk: Vec<&str> = vec![];
+        for line in text.lines() {
+            if !chunk.is_empty() && Self::size(&chunk) + line.len() >= self.splitter_chunk_size {
+                let overlap = self.overlap(&chunk);
+                chunks.push(chunk.join("\n"));
+                chunk = overlap;
+
+                // Drop the overlap if the chunk can't hold it together with the line.
+                if Self::size(&chunk) + line.len() >= self.splitter_chunk_size {
+                    chunk.clear();
+                }
+            }
+            chunk.push(line);
+        }
+        if !chunk.is_empty() {
+            chunks.push(chunk.join("\n"));
+        }
+        chunks.retain(|x| !x.trim().is_empty());
+        chunks
+    }
+
+    fn size(lines: &[&str]) -> usize {
+        lines.iter().map(|x| x.len() + 1).sum()
+    }
+
+    /// The trailing lines of the chunk within the overlap size.
+    fn overlap<'a>(&self, chunk: &[&'a str]) -> Vec<&'a str> {
+        let mut size = 0;
+        let mut overlap = vec![];
+        for line in chunk.iter().rev() {
+            size += line.len() + 1;
+            if size > self.chunk_overlap {
+                break;
+            }
+            overlap.push(*line);
+        }
+        overlap.reverse();
+        overlap
+    }
+}
+
+impl DocumentSplitter for QATextSplitter {
+    fn separators(&self) -> Vec<String> {
+        vec!["\n".to_string()]
+    }
+
+    fn split_documents(&self, documents: &Documents) -> Result<Documents> {
+        let result = Documents::create();
+        for document in documents {
+            for chunk in self.split_text(&document.content) {
+                result.push(Document::create(&document.path, &chunk));
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use databend_driver::Client;
+use futures::StreamExt;
+use llmchain::DatabendEmbedding;
+use llmchain::Document;
+use llmchain::Documents;
+use llmchain::Embedding;
+use log::info;
+
+use crate::base::escape_sql_string;
+use crate::qa::QAChunk;
+use crate::qa::QASearchFilter;
+use crate::Config;
+
+/// Metadata columns of the chunks and their types, added to the tables created before them.
+const METADATA_COLUMNS: [(&str, &str); 9] = [
+    ("title", "VARCHAR"),
+    ("slug", "VARCHAR"),
+    ("headings", "VARCHAR"),
+    ("chunk_index", "BIGINT UNSIGNED"),
+    ("section_id", "BIGINT UNSIGNED"),
+    ("doc_section", "VARCHAR"),
+    ("doc_path", "VARCHAR"),
+    ("version", "VARCHAR"),
+    ("tags", "VARCHAR"),
+];
+
+/// Columns of the chunks read by the searches, followed by the similarity.
+const CHUNK_COLUMNS: &str = "path, content, title, slug, headings, chunk_index, section_id, doc_section, doc_path, version, tags";
+
+/// Vector store of the chunks and their metadata in Databend.
+pub struct QAVectorStore {
+    client: Client,
+    database: String,
+    table: String,
+    embedding: Arc<dyn Embedding>,
+    min_similarity: f32,
+}
+
+impl QAVectorStore {
+    pub fn create(conf: &Config) -> Self {
+        QAVectorStore {
+            client: Client::new(conf.qa.dsn.clone()),
+            database: conf.qa.database.clone(),
+            table: conf.qa.table.clone(),
+            embedding: Arc::new(DatabendEmbedding::create(&conf.qa.dsn)),
+            min_similarity: 0.5,
+        }
+    }
+
+    /// The chunks less similar to the query are never returned.
+    pub fn min_similarity(&self) -> f32 {
+        self.min_similarity
+    }
+
+    pub async fn init(&self) -> Result<()> {
+        let conn = self.client.get_conn().await?;
+
+        let sql = format!("CREATE DATABASE IF NOT EXISTS {}", self.database);
+        conn.exec(&sql).await?;
+
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {}.{} \
+            (uuid VARCHAR, path VARCHAR, content VARCHAR, content_md5 VARCHAR, embedding ARRAY(float32), \
+            title VARCHAR, slug VARCHAR, headings VARCHAR, chunk_index BIGINT UNSIGNED, section_id BIGINT UNSIGNED, \
+            doc_section VARCHAR, doc_path VARCHAR, version VARCHAR, tags VARCHAR)",
+            self.database, self.table
+        );
+        conn.exec(&sql).await?;
+
+        let sql = format!(
+            "SELECT name FROM system.columns WHERE database = '{}' AND table = '{}'",
+            escape_sql_string(&self.database),
+            escape_sql_string(&self.table)
+        );
+        let mut columns = vec![];
+        let mut rows = conn.query_iter(&sql).await?;
+        while let Some(row) = rows.next().await {
+            let (name,): (String,) = row?.try_into().map_err(|e: String| anyhow!(e))?;
+            columns.push(name);
+        }
+        for (column, data_type) in METADATA_COLUMNS {
+            if !columns.iter().any(|x| x == column) {
+                info!("add column {} to {}.{}", column, self.database, self.table);
+                let sql = format!(
+                    "ALTER TABLE {}.{} ADD COLUMN {} {}",
+                    self.database, self.table, column, data_type
+                );
+                conn.exec(&sql).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn add_chunks(&self, chunks: &[QAChunk]) -> Result<()> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+
+        let documents = Documents::from(
+            chunks
+                .iter()
+                .map(|x| Document::create(&x.path, &x.content))
+                .collect::<Vec<_>>(),
+        );
+        let embeddings = self.embedding.embed_documents(&documents).await?;
+        if embeddings.len() != chunks.len() {
+            return Err(anyhow!(
+                "expect {} embeddings, got {}",
+                chunks.len(),
+                embeddings.len()
+            ));
+        }
+
+        let values = chunks
+            .iter()
+            .zip(documents.iter())
+            .zip(embeddings.iter())
+            .map(|((chunk, document), embedding)| {
+                format!(
+                    "('{}', '{}', '{}', '{}', {:?}, '{}', '{}', '{}', {}, {}, '{}', '{}', '{}', '{}')",
+                    uuid::Uuid::new_v4(),
+                    escape_sql_string(&chunk.path),
+                    escape_sql_string(&chunk.content),
+                    document.content_md5,
+                    embedding,
+                    escape_sql_string(&chunk.title),
+                    escape_sql_string(&chunk.slug),
+                    escape_sql_string(&chunk.headings),
+                    chunk.index,
+                    chunk.section_id,
+                    escape_sql_string(&chunk.doc_section),
+                    escape_sql_string(&chunk.doc_path),
+                    escape_sql_string(&chunk.version),
+                    escape_sql_string(&chunk.tags.join(",")),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let sql = format!(
+            "INSERT INTO {}.{} (uuid, path, content, content_md5, embedding, title, slug, headings, chunk_index, section_id, doc_section, doc_path, version, tags) VALUES {}",
+            self.database, self.table, values
+        );
+        let conn = self.client.get_conn().await?;
+        conn.exec(&sql).await?;
+        Ok(())
+    }
+
+    /// Add the chunks after deleting their rows added before, eg: by a batch interrupted
+    /// before it's marked done in the checkpoint.
+    pub async fn replace_chunks(&self, chunks: &[QAChunk]) -> Result<()> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+
+        let conditions = chunks
+            .iter()
+            .map(|x| {
+                format!(
+                    "(path = '{}' AND chunk_index = {})",
+                    escape_sql_string(&x.path),
+                    x.index
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!(
+            "DELETE FROM {}.{} WHERE {}",
+            self.database, self.table, conditions
+        );
+        let conn = self.client.get_conn().await?;
+        conn.exec(&sql).await?;
+        self.add_chunks(chunks).await
+    }
+
+    /// The top `k` chunks most similar to the query, most similar first.
+    pub async fn similarity_search(&self, query: &str, k: usize) -> Result<Vec<QAChunk>> {
+        self.filtered_search(query, &QASearchFilter::default(), 0, k)
+            .await
+    }
+
+    /// The chunks matching the filter most similar to the query, skipping the `offset` most similar ones.
+    pub async fn filtered_search(
+        &self,
+        query: &str,
+        filter: &QASearchFilter,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<QAChunk>> {
+        let query_embedding = self.embedding.embed_query(query).await?;
+
+        let sql = format!(
+            "SELECT {}, (1 - cosine_distance({:?}, embedding)) AS similarity FROM {}.{} \
+             WHERE length(embedding) > 0 AND length(content) > 0 AND similarity > {}{} ORDER BY similarity DESC LIMIT {} OFFSET {}",
+            CHUNK_COLUMNS,
+            query_embedding,
+            self.database,
+            self.table,
+            self.min_similarity,
+            filter.conditions(),
+            limit,
+            offset
+        );
+        info!("similarity_search from {}.{}", self.database, self.table);
+
+        let chunks = self.query_chunks(&sql).await?;
+        info!("Found {} chunks", chunks.len());
+        Ok(chunks)
+    }
+
+    /// The chunks at most `n` positions before or after the chunks in their documents, in document order.
+    /// The chunks embedded before the positions were stored have no neighbors or sections.
+    pub async fn neighbors(&self, chunks: &[QAChunk], n: usize) -> Result<Vec<QAChunk>> {
+        if n == 0 {
+            return Ok(vec![]);
+        }
+        let conditions = chunks
+            .iter()
+            .map(|x| {
+                format!(
+                    "(path = '{}' AND chunk_index BETWEEN {} AND {})",
+                    escape_sql_string(&x.path),
+                    x.index.saturating_sub(n),
+                    x.index + n
+                )
+            })
+            .collect::<Vec<_>>();
+        self.document_chunks(&conditions).await
+    }
+
+    /// All the chunks of the parent sections of the chunks, in document order.
+    pub async fn sections(&self, chunks: &[QAChunk]) -> Result<Vec<QAChunk>> {
+        let conditions = chunks
+            .iter()
+            .map(|x| {
+                format!(
+                    "(path = '{}' AND section_id = {})",
+                    escape_sql_string(&x.path),
+                    x.section_id
+                )
+            })
+            .collect::<Vec<_>>();
+        self.document_chunks(&conditions).await
+    }
+
+    /// The chunks with their positions matching any of the conditions.
+    async fn document_chunks(&self, conditions: &[String]) -> Result<Vec<QAChunk>> {
+        if conditions.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            "SELECT {}, 0::FLOAT32 FROM {}.{} \
+             WHERE length(content) > 0 AND ({}) ORDER BY path, chunk_index",
+            CHUNK_COLUMNS,
+            self.database,
+            self.table,
+            conditions.join(" OR ")
+        );
+        self.query_chunks(&sql).await
+    }
+
+    async fn query_chunks(&self, sql: &str) -> Result<Vec<QAChunk>> {
+        type RowResult = (
+            String,
+            String,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<u64>,
+            Option<u64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            f32,
+        );
+        let mut chunks = vec![];
+        let conn = self.client.get_conn().await?;
+        let mut rows = conn.query_iter(sql).await?;
+        while let Some(row) = rows.next().await {
+            let row: RowResult = row?.try_into().map_err(|e: String| anyhow!(e))?;
+            chunks.push(QAChunk {
+                path: row.0,
+                content: row.1,
+                title: row.2.unwrap_or_default(),
+                slug: row.3.unwrap_or_default(),
+                headings: row.4.unwrap_or_default(),
+                index: row.5.unwrap_or_default() as usize,
+                section_id: row.6.unwrap_or_default() as usize,
+                doc_section: row.7.unwrap_or_default(),
+                doc_path: row.8.unwrap_or_default(),
+                version: row.9.unwrap_or_default(),
+                tags: row
+                    .10
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string())
+                    .collect(),
+                similarity: row.11,
+                ..Default::default()
+            });
+        }
+        Ok(chunks)
+    }
+}
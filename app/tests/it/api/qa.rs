@@ -0,0 +1,46 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use actix_web::web;
+use actix_web::App;
+use askbend::qa_search_handler;
+use askbend::Config;
+
+#[actix_web::test]
+async fn test_qa_search_handler_bad_page() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(Config::default()))
+            .route("/qa/search", web::post().to(qa_search_handler)),
+    )
+    .await;
+
+    // Rejected before searching.
+    for body in [
+        r#"{"query": "copy", "page": 101}"#,
+        r#"{"query": "copy", "page": 18446744073709551615}"#,
+        r#"{"query": "copy", "page": 0}"#,
+        r#"{"query": "copy", "page_size": 51}"#,
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/qa/search")
+            .insert_header(("content-type", "application/json"))
+            .set_payload(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", body);
+    }
+}
@@ -11,3 +11,28 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use askbend::escape_sql_array;
+use askbend::escape_sql_like;
+use askbend::escape_sql_string;
+
+#[test]
+fn test_escape_sql_like() {
+    assert_eq!(
+        escape_sql_like("12-load_data/100%"),
+        "12-load\\_data/100\\%"
+    );
+    assert_eq!(
+        escape_sql_string(&escape_sql_like("it's a\\b")),
+        "it''s a\\\\\\\\b"
+    );
+}
+
+#[test]
+fn test_escape_sql_array() {
+    assert_eq!(escape_sql_array(&[]), "[]");
+    assert_eq!(
+        escape_sql_array(&["COPY INTO".to_string(), "what's a stage".to_string()]),
+        "['COPY INTO','what''s a stage']"
+    );
+}
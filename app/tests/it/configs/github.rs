@@ -0,0 +1,202 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::GithubConfig;
+use askbend::GithubRepoConfig;
+
+#[test]
+fn test_github_repo_config() {
+    let mut conf = Config::default();
+    conf.github.repos = Some(vec!["https://github.com/a/b".to_string()]);
+    conf.github.repo_configs = vec![
+        GithubRepoConfig {
+            repo: "https://github.com/a/b".to_string(),
+            commands: vec!["summary".to_string()],
+            llm_max_tokens: 2000,
+            ..Default::default()
+        },
+        GithubRepoConfig {
+            repo: "https://github.com/a/c".to_string(),
+            ..Default::default()
+        },
+    ];
+    assert_eq!(conf.github.all_repos(), vec![
+        "https://github.com/a/b".to_string(),
+        "https://github.com/a/c".to_string()
+    ]);
+
+    let repo_config = conf.github.repo_config("https://github.com/a/b");
+    assert!(repo_config.is_enabled("summary"));
+    assert!(!repo_config.is_enabled("review"));
+    assert_eq!(repo_config.max_tokens(&conf.github), 2000);
+
+    let repo_config = conf.github.repo_config("https://github.com/x/y");
+    assert_eq!(repo_config.repo, "https://github.com/x/y");
+    assert!(repo_config.is_enabled("review"));
+    assert_eq!(repo_config.max_tokens(&conf.github), 100000);
+    assert_eq!(repo_config.chunk_size, 8000);
+}
+
+#[test]
+fn test_github_repo_config_file() {
+    let content = r#"
+commands = ["review"]
+allowed_teams = ["databendlabs/maintainers"]
+ignored_paths = ["Cargo.lock"]
+"#;
+    let repo_config: GithubRepoConfig = toml::from_str(content).unwrap();
+    assert!(repo_config.is_enabled("review"));
+    assert_eq!(repo_config.chunk_size, 8000);
+
+    let mut conf = Config::default();
+    conf.github.generated_paths = vec!["**/*.pb.go".to_string()];
+    assert_eq!(repo_config.diff_skips(&conf.github), vec![
+        "**/*.pb.go".to_string(),
+        "[ab]/**/*.pb.go".to_string(),
+        "Cargo.lock".to_string(),
+        "[ab]/Cargo.lock".to_string()
+    ]);
+
+    assert!(toml::from_str::<GithubRepoConfig>("unknown = 1").is_err());
+}
+
+#[test]
+fn test_github_repo_config_merge_file() {
+    let repo_config = GithubRepoConfig {
+        repo: "owner/repo".to_string(),
+        commands: vec!["summary".to_string(), "review".to_string()],
+        allowed_users: vec!["alice".to_string()],
+        ignored_paths: vec!["Cargo.lock".to_string()],
+        ..Default::default()
+    };
+
+    // The file without access lists doesn't open the access to anyone.
+    let file: GithubRepoConfig = toml::from_str(
+        "commands = [\"review\"]\nlanguage = \"Chinese\"\nignored_paths = [\"*.pb.go\"]",
+    )
+    .unwrap();
+    let merged = repo_config.merge_file(file);
+    assert_eq!(merged.repo, "owner/repo");
+    assert_eq!(merged.commands, vec!["review".to_string()]);
+    assert_eq!(merged.allowed_users, vec!["alice".to_string()]);
+    assert_eq!(merged.language, "Chinese");
+    assert_eq!(merged.chunk_size, 8000);
+    assert_eq!(merged.ignored_paths, vec![
+        "Cargo.lock".to_string(),
+        "*.pb.go".to_string()
+    ]);
+
+    // The file can't replace the access lists or enable other commands.
+    let file: GithubRepoConfig =
+        toml::from_str("commands = [\"deploy\"]\nallowed_users = [\"mallory\"]").unwrap();
+    let merged = repo_config.merge_file(file);
+    assert_eq!(merged.allowed_users, vec!["alice".to_string()]);
+    assert_eq!(merged.commands, repo_config.commands);
+
+    // The file narrows the access of a config allowing anyone.
+    let file: GithubRepoConfig = toml::from_str("allowed_teams = [\"org/team\"]").unwrap();
+    let merged = GithubRepoConfig::default().merge_file(file);
+    assert_eq!(merged.allowed_teams, vec!["org/team".to_string()]);
+}
+
+#[test]
+fn test_github_parse_repo() {
+    let cases = vec![
+        ("owner/repo", Some(("owner", "repo"))),
+        ("github.com/owner/repo", Some(("owner", "repo"))),
+        ("https://github.com/owner/repo", Some(("owner", "repo"))),
+        ("https://github.com/owner/repo/", Some(("owner", "repo"))),
+        ("https://github.com/owner/repo.git", Some(("owner", "repo"))),
+        (
+            "https://github.com/owner/repo/pull/1",
+            Some(("owner", "repo")),
+        ),
+        (
+            "https://github.example.com/owner/my.repo",
+            Some(("owner", "my.repo")),
+        ),
+        ("git@github.com:owner/repo.git", Some(("owner", "repo"))),
+        (
+            "ssh://git@github.com/owner/repo.git",
+            Some(("owner", "repo")),
+        ),
+        (" owner/repo ", Some(("owner", "repo"))),
+        ("https://github.com/owner", None),
+        ("owner", None),
+        ("", None),
+        ("owner/re po", None),
+        ("/repo", None),
+    ];
+    for (repo, expect) in cases {
+        let actual = GithubConfig::parse_repo(repo).ok();
+        let expect = expect.map(|(o, r)| (o.to_string(), r.to_string()));
+        assert_eq!(actual, expect, "repo: {:?}", repo);
+    }
+}
+
+#[test]
+fn test_github_config_validate() {
+    let mut conf = Config::default();
+    conf.github.repos = Some(vec![
+        "owner/repo".to_string(),
+        "https://github.com/owner".to_string(),
+    ]);
+    conf.github.repo_configs = vec![
+        GithubRepoConfig {
+            repo: "git@github.com:owner/repo.git".to_string(),
+            commands: vec!["summary".to_string()],
+            ..Default::default()
+        },
+        GithubRepoConfig {
+            repo: "bad".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let err = conf.github.validate().unwrap_err().to_string();
+    assert!(err.contains("\"https://github.com/owner\""));
+    assert!(err.contains("\"bad\""));
+    assert!(!err.contains("owner/repo\""));
+
+    // The same repo in different forms.
+    conf.github.repos = Some(vec!["owner/repo".to_string()]);
+    conf.github.repo_configs.pop();
+    assert!(conf.github.validate().is_ok());
+
+    conf.github.review_min_severity = "HIGH".to_string();
+    assert!(conf.github.validate().is_ok());
+    conf.github.review_min_severity = "hihg".to_string();
+    let err = format!("{:?}", conf.github.validate().unwrap_err());
+    assert!(err.contains("review_min_severity"));
+    assert!(err.contains("hihg"));
+    conf.github.review_min_severity = "medium".to_string();
+
+    assert_eq!(conf.github.all_repos(), vec!["owner/repo".to_string()]);
+    assert!(
+        !conf
+            .github
+            .repo_config("https://github.com/Owner/repo")
+            .is_enabled("review")
+    );
+
+    // A typo in the commands would disable the command.
+    conf.github.repo_configs = vec![GithubRepoConfig {
+        repo: "owner/repo".to_string(),
+        commands: vec!["sumary".to_string()],
+        ..Default::default()
+    }];
+    let err = conf.github.validate().unwrap_err().to_string();
+    assert!(err.contains("sumary"));
+}
@@ -0,0 +1,126 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::QACollectionConfig;
+
+fn collections_conf() -> Config {
+    let mut conf = Config::default();
+    conf.qa.database = "askbend".to_string();
+    conf.qa.table = "doc".to_string();
+    conf.qa.source_base_url = "https://docs.databend.com".to_string();
+    conf.qa.docs_root = "docs".to_string();
+    conf.qa.collections = vec![
+        QACollectionConfig {
+            name: "runbooks".to_string(),
+            path: "/data/runbooks/".to_string(),
+            table: "runbooks".to_string(),
+            top: 5,
+            instructions: vec!["Answer with the steps of the runbook.".to_string()],
+            search_weight: 80,
+            ..Default::default()
+        },
+        QACollectionConfig {
+            name: "sdk".to_string(),
+            git_url: "https://github.com/datafuselabs/bendsql".to_string(),
+            database: "sdk".to_string(),
+            table: "doc".to_string(),
+            ..Default::default()
+        },
+    ];
+    conf
+}
+
+#[test]
+fn test_qa_collection_config() {
+    let conf = collections_conf();
+    assert!(conf.qa.validate().is_ok());
+    assert_eq!(conf.qa.collection_names(), vec!["runbooks", "sdk"]);
+
+    // The default collection.
+    assert_eq!(conf.with_collection("").unwrap(), conf);
+    assert_eq!(conf.with_collection("default").unwrap(), conf);
+
+    let runbooks = conf.qa.collection_config("runbooks").unwrap();
+    assert_eq!(runbooks.collection, "runbooks");
+    assert_eq!(runbooks.path, "/data/runbooks/");
+    assert_eq!(runbooks.database, "askbend");
+    assert_eq!(runbooks.table, "runbooks");
+    assert_eq!(runbooks.top, 5);
+    assert_eq!(runbooks.instructions, vec![
+        "Answer with the steps of the runbook."
+    ]);
+    assert_eq!(runbooks.search_weight, 80);
+    assert_eq!(runbooks.git_checkout_dir, ".askbend/git_source/runbooks/");
+    assert_eq!(
+        runbooks.rebuild_checkpoint,
+        ".askbend/rebuild_checkpoint.runbooks.json"
+    );
+
+    // The source fields are not inherited.
+    assert_eq!(runbooks.git_url, "");
+    assert_eq!(runbooks.source_base_url, "");
+    assert_eq!(runbooks.docs_root, "");
+
+    // The other empty fields are inherited.
+    let sdk = conf.with_collection("sdk").unwrap().qa;
+    assert_eq!(sdk.git_url, "https://github.com/datafuselabs/bendsql");
+    assert_eq!(sdk.git_branch, "main");
+    assert_eq!(sdk.path, "");
+    assert_eq!(sdk.database, "sdk");
+    assert_eq!(sdk.top, conf.qa.top);
+    assert_eq!(sdk.instructions, conf.qa.instructions);
+    assert_eq!(sdk.search_weight, 100);
+
+    let err = conf.with_collection("blog").unwrap_err();
+    assert!(err.to_string().contains("unknown collection: blog"));
+}
+
+#[test]
+fn test_qa_collection_config_validate() {
+    let mut conf = collections_conf();
+    conf.qa.collections.push(QACollectionConfig {
+        name: "sdk".to_string(),
+        table: "sdk".to_string(),
+        ..Default::default()
+    });
+    conf.qa.collections.push(QACollectionConfig {
+        name: "public".to_string(),
+        ..Default::default()
+    });
+    conf.qa.collections.push(QACollectionConfig {
+        table: "x".to_string(),
+        ..Default::default()
+    });
+    conf.qa.collections.push(QACollectionConfig {
+        name: "default".to_string(),
+        table: "y".to_string(),
+        ..Default::default()
+    });
+
+    let err = conf.qa.validate().unwrap_err().to_string();
+    assert!(err.contains("\"sdk\": duplicate name"), "{}", err);
+    assert!(
+        err.contains("\"public\": table askbend.doc is used by another collection"),
+        "{}",
+        err
+    );
+    assert!(err.contains("collection #4: missing name"), "{}", err);
+    assert!(err.contains("\"default\": reserved name"), "{}", err);
+    assert!(
+        err.contains("\"public\": missing path or git_url"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,31 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+
+#[test]
+fn test_server_config_admin_keys() {
+    let mut conf = Config::default();
+    assert!(!conf.server.is_admin("Bearer "));
+    assert!(!conf.server.is_admin("Bearer admin-key"));
+
+    conf.server.admin_keys = vec!["admin-key".to_string()];
+    assert!(conf.server.is_admin("Bearer admin-key"));
+    assert!(!conf.server.is_admin("admin-key"));
+    assert!(!conf.server.is_admin("Bearer other-key"));
+    assert!(!conf.server.is_admin("Basic admin-key"));
+
+    // The keys are never logged.
+    assert!(!format!("{:?}", conf).contains("admin-key"));
+}
@@ -0,0 +1,158 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Read;
+use std::net::TcpListener;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use askbend::Config;
+use askbend::GithubClient;
+
+use crate::github::mock_github::MockGithub;
+
+#[tokio::test]
+async fn test_github_client_create() {
+    let mut conf = Config::default();
+    conf.github.github_token = "token".to_string();
+    conf.github.api_base_url = "https://github.example.com/api/v3".to_string();
+    assert!(GithubClient::create(&conf.github).is_ok());
+
+    conf.github.api_base_url = "not a url".to_string();
+    assert!(GithubClient::create(&conf.github).is_err());
+
+    conf.github.api_base_url = "".to_string();
+    conf.github.app_id = 1;
+    conf.github.app_private_key_path = "tests/testdata/not_exists.pem".to_string();
+    let err = GithubClient::create(&conf.github).err().unwrap();
+    assert!(err.to_string().contains("not_exists.pem"));
+}
+
+#[tokio::test]
+async fn test_github_client_rate_limit() {
+    let mock = MockGithub::start();
+    mock.respond(
+        "GET",
+        "/repos/owner/repo/pulls",
+        429,
+        &[("retry-after", "1")],
+        r#"{"message":"You have exceeded a secondary rate limit"}"#,
+    )
+    .respond(
+        "GET",
+        "/repos/owner/repo/pulls",
+        500,
+        &[],
+        r#"{"message":"Server Error"}"#,
+    )
+    .fixture("GET", "/repos/owner/repo/pulls", "pulls.json");
+
+    let mut conf = Config::default();
+    conf.github.github_token = "token".to_string();
+    conf.github.api_base_url = mock.url.clone();
+    conf.github.retry_delay_ms = 10;
+    let client = GithubClient::create(&conf.github)
+        .unwrap()
+        .repo("owner", "repo")
+        .await
+        .unwrap();
+
+    // Retried after the rate limit and the server error.
+    let pulls = client.list_open_pulls().await.unwrap();
+    assert_eq!(pulls.len(), 1);
+    assert_eq!(pulls[0].number, 1);
+    assert_eq!(mock.requests("GET", "/repos/owner/repo/pulls").len(), 3);
+
+    // Gives up after the max retries.
+    conf.github.max_retries = 1;
+    mock.respond("GET", "/repos/owner/other/pulls", 502, &[], "{}");
+    let client = GithubClient::create(&conf.github)
+        .unwrap()
+        .repo("owner", "other")
+        .await
+        .unwrap();
+    assert!(client.list_open_pulls().await.is_err());
+    assert_eq!(mock.requests("GET", "/repos/owner/other/pulls").len(), 2);
+
+    // A POST may have been written by the server error, it's not retried.
+    mock.respond(
+        "POST",
+        "/repos/owner/other/issues/1/comments",
+        502,
+        &[],
+        "{}",
+    );
+    assert!(client.create_comment(1, "comment").await.is_err());
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/other/issues/1/comments")
+            .len(),
+        1
+    );
+
+    // A rate limited POST is not written, it's retried.
+    mock.respond(
+        "POST",
+        "/repos/owner/other/issues/2/comments",
+        429,
+        &[("retry-after", "1")],
+        "{}",
+    )
+    .respond(
+        "POST",
+        "/repos/owner/other/issues/2/comments",
+        201,
+        &[],
+        "{}",
+    );
+    client.create_comment(2, "comment").await.unwrap();
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/other/issues/2/comments")
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_github_client_transport_error() {
+    // A server reading the requests and closing the connections without a response.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.read(&mut [0; 4096]);
+            accepted.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let mut conf = Config::default();
+    conf.github.github_token = "token".to_string();
+    conf.github.api_base_url = url;
+    conf.github.max_retries = 2;
+    conf.github.retry_delay_ms = 10;
+    let client = GithubClient::create(&conf.github)
+        .unwrap()
+        .repo("owner", "repo")
+        .await
+        .unwrap();
+
+    // The POST may have been written, it's not retried.
+    assert!(client.create_comment(1, "comment").await.is_err());
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    assert!(client.list_open_pulls().await.is_err());
+    assert_eq!(connections.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,38 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::GithubCommand;
+
+#[test]
+fn test_github_command_parse() {
+    assert_eq!(
+        GithubCommand::parse("askbend:summary"),
+        Some(GithubCommand::Summary)
+    );
+    assert_eq!(
+        GithubCommand::parse(" /askbend summary\n"),
+        Some(GithubCommand::Summary)
+    );
+    assert_eq!(
+        GithubCommand::parse("/askbend review"),
+        Some(GithubCommand::Review)
+    );
+    assert_eq!(
+        GithubCommand::parse("askbend:review"),
+        Some(GithubCommand::Review)
+    );
+    assert_eq!(GithubCommand::parse("/askbendreview"), None);
+    assert_eq!(GithubCommand::parse("/askbend deploy"), None);
+    assert_eq!(GithubCommand::parse("LGTM"), None);
+}
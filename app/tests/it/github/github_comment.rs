@@ -0,0 +1,443 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::GithubClient;
+use askbend::GithubCommand;
+use askbend::GithubComment;
+use askbend::GithubRepoConfig;
+use askbend::ScanCursor;
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::github::mock_github::MockGithub;
+
+fn mock_conf(mock: &MockGithub) -> Config {
+    let mut conf = Config::default();
+    conf.github.github_token = "token".to_string();
+    conf.github.api_base_url = mock.url.clone();
+    conf.github.max_retries = 0;
+    conf
+}
+
+fn since() -> DateTime<Utc> {
+    "2023-07-01T00:00:00Z".parse().unwrap()
+}
+
+#[tokio::test]
+async fn test_github_comment_triggers() {
+    let mock = MockGithub::start();
+    mock.fixture(
+        "GET",
+        "/repos/owner/repo/issues/1/comments",
+        "comments.json",
+    )
+    .respond(
+        "POST",
+        "/repos/owner/repo/issues/comments/11/reactions",
+        201,
+        &[],
+        "{}",
+    )
+    .respond(
+        "POST",
+        "/repos/owner/repo/issues/comments/13/reactions",
+        201,
+        &[],
+        "{}",
+    );
+    let conf = mock_conf(&mock);
+    let client = GithubClient::create(&conf.github)
+        .unwrap()
+        .repo("owner", "repo")
+        .await
+        .unwrap();
+
+    // Newest first, unknown commands are ignored.
+    let repo = "https://github.com/owner/repo";
+    let repo_config = GithubRepoConfig::default();
+    let mut cursor = ScanCursor::create(since());
+    let commands = GithubComment::triggers(&client, &repo_config, repo, 1, &mut cursor)
+        .await
+        .unwrap();
+    assert_eq!(commands, vec![
+        GithubCommand::Review,
+        GithubCommand::Summary
+    ]);
+    assert_eq!(
+        cursor.since(repo, 1),
+        "2023-07-01T04:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+
+    let requests = mock.requests("GET", "/repos/owner/repo/issues/1/comments");
+    assert!(requests[0].query.contains("since=2023-07-01T00:00:00Z"));
+    let reactions = mock.requests("POST", "/repos/owner/repo/issues/comments/13/reactions");
+    assert_eq!(reactions.len(), 1);
+    assert!(reactions[0].body.contains("+1"));
+
+    // The comments scanned are skipped, the API lists them again.
+    let commands = GithubComment::triggers(&client, &repo_config, repo, 1, &mut cursor)
+        .await
+        .unwrap();
+    assert!(commands.is_empty());
+    let requests = mock.requests("GET", "/repos/owner/repo/issues/1/comments");
+    assert!(requests[1].query.contains("since=2023-07-01T04:00:00Z"));
+
+    // Disabled commands and users not allowed are skipped.
+    let repo_config = GithubRepoConfig {
+        commands: vec!["summary".to_string()],
+        allowed_users: vec!["alice".to_string()],
+        ..Default::default()
+    };
+    let mut cursor = ScanCursor::create(since());
+    let commands = GithubComment::triggers(&client, &repo_config, repo, 1, &mut cursor)
+        .await
+        .unwrap();
+    assert_eq!(commands, vec![GithubCommand::Summary]);
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/13/reactions")
+            .len(),
+        1
+    );
+
+    let repo_config = GithubRepoConfig {
+        allowed_users: vec!["dave".to_string()],
+        ..Default::default()
+    };
+    let mut cursor = ScanCursor::create(since());
+    let commands = GithubComment::triggers(&client, &repo_config, repo, 1, &mut cursor)
+        .await
+        .unwrap();
+    assert!(commands.is_empty());
+
+    // The comments created before the start are skipped, the API lists the updated ones too.
+    let mut cursor = ScanCursor::create("2023-07-01T03:00:00Z".parse().unwrap());
+    let commands =
+        GithubComment::triggers(&client, &GithubRepoConfig::default(), repo, 1, &mut cursor)
+            .await
+            .unwrap();
+    assert!(commands.is_empty());
+}
+
+#[tokio::test]
+async fn test_github_comment_post_summary() {
+    let mock = MockGithub::start();
+    mock.fixture(
+        "GET",
+        "/repos/owner/repo/issues/1/comments",
+        "summary_comments.json",
+    )
+    .respond(
+        "PATCH",
+        "/repos/owner/repo/issues/comments/22",
+        200,
+        &[],
+        "{}",
+    )
+    .respond("GET", "/repos/owner/new/issues/1/comments", 200, &[], "[]")
+    .respond("POST", "/repos/owner/new/issues/1/comments", 201, &[], "{}")
+    .respond("GET", "/user", 200, &[], r#"{"login": "askbend-bot"}"#);
+    let conf = mock_conf(&mock);
+    let client = GithubClient::create(&conf.github).unwrap();
+    let sha = "1234567890abcdef1234567890abcdef12345678";
+
+    // The previous summary comment is edited in place.
+    let repo_client = client.repo("owner", "repo").await.unwrap();
+    GithubComment::post_summary_comment(&repo_client, 1, sha, "new summary")
+        .await
+        .unwrap();
+    let updates = mock.requests("PATCH", "/repos/owner/repo/issues/comments/22");
+    assert_eq!(updates.len(), 1);
+    assert!(updates[0].body.contains(sha));
+    assert!(updates[0].body.contains("new summary"));
+    assert!(
+        mock.requests("POST", "/repos/owner/repo/issues/1/comments")
+            .is_empty()
+    );
+
+    // A new comment is created if there is no summary comment.
+    let repo_client = client.repo("owner", "new").await.unwrap();
+    GithubComment::post_summary_comment(&repo_client, 1, sha, "new summary")
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/new/issues/1/comments")
+            .len(),
+        1
+    );
+
+    // The summary comments of the other users are not edited.
+    let comments = std::fs::read_to_string("tests/testdata/github/summary_comments.json")
+        .unwrap()
+        .replace("askbend-bot", "mallory");
+    mock.respond(
+        "GET",
+        "/repos/owner/other/issues/1/comments",
+        200,
+        &[],
+        &comments,
+    )
+    .respond(
+        "POST",
+        "/repos/owner/other/issues/1/comments",
+        201,
+        &[],
+        "{}",
+    );
+    let repo_client = client.repo("owner", "other").await.unwrap();
+    GithubComment::post_summary_comment(&repo_client, 1, sha, "new summary")
+        .await
+        .unwrap();
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/other/issues/1/comments")
+            .len(),
+        1
+    );
+    assert!(
+        mock.requests("PATCH", "/repos/owner/other/issues/comments/22")
+            .is_empty()
+    );
+    // The bot login is requested once.
+    assert_eq!(mock.requests("GET", "/user").len(), 1);
+}
+
+#[tokio::test]
+async fn test_github_comment_scan_repo_pull_cursors() {
+    let fixture = |file: &str| -> Vec<serde_json::Value> {
+        let content = std::fs::read_to_string(format!("tests/testdata/github/{}", file)).unwrap();
+        serde_json::from_str(&content).unwrap()
+    };
+    let mut pulls = fixture("pulls.json");
+    let mut pull = pulls[0].clone();
+    pull["number"] = 2.into();
+    pulls.push(pull);
+    let comments = fixture("comments.json");
+    let review = |id: u64, created_at: &str| {
+        let mut comment = comments[2].clone();
+        comment["id"] = id.into();
+        comment["created_at"] = created_at.into();
+        comment["updated_at"] = created_at.into();
+        serde_json::to_string(&vec![comment]).unwrap()
+    };
+
+    // #1 is commented at 02:00 after it's listed, #2 has a newer comment of 03:00.
+    let mock = MockGithub::start();
+    mock.respond(
+        "GET",
+        "/repos/owner/repo/pulls",
+        200,
+        &[],
+        &serde_json::to_string(&pulls).unwrap(),
+    )
+    .respond("GET", "/repos/owner/repo/issues/1/comments", 200, &[], "[]")
+    .respond(
+        "GET",
+        "/repos/owner/repo/issues/1/comments",
+        200,
+        &[],
+        &review(15, "2023-07-01T02:00:00Z"),
+    )
+    .respond(
+        "GET",
+        "/repos/owner/repo/issues/2/comments",
+        200,
+        &[],
+        &review(16, "2023-07-01T03:00:00Z"),
+    )
+    .respond(
+        "POST",
+        "/repos/owner/repo/issues/comments/15/reactions",
+        201,
+        &[],
+        "{}",
+    )
+    .respond(
+        "POST",
+        "/repos/owner/repo/issues/comments/16/reactions",
+        201,
+        &[],
+        "{}",
+    );
+    let comment = GithubComment::create(&mock_conf(&mock)).unwrap();
+
+    let repo = "https://github.com/owner/repo";
+    let mut cursor = ScanCursor::create(since());
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/16/reactions")
+            .len(),
+        1
+    );
+
+    // The comment of #1 is older than the one of #2, it's still scanned once.
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/15/reactions")
+            .len(),
+        1
+    );
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/16/reactions")
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_github_comment_repo_config_file() {
+    let mock = MockGithub::start();
+    mock.fixture("GET", "/repos/owner/repo/pulls", "pulls.json")
+        .fixture(
+            "GET",
+            "/repos/owner/repo/issues/1/comments",
+            "comments.json",
+        )
+        .respond(
+            "GET",
+            "/repos/owner/repo/contents/.askbend.toml",
+            200,
+            &[],
+            "language = \"Chinese\"",
+        )
+        .respond(
+            "POST",
+            "/repos/owner/repo/issues/comments/13/reactions",
+            201,
+            &[],
+            "{}",
+        );
+    let mut conf = mock_conf(&mock);
+    conf.github.repo_configs = vec![GithubRepoConfig {
+        repo: "https://github.com/owner/repo".to_string(),
+        allowed_users: vec!["bob".to_string()],
+        ..Default::default()
+    }];
+    let comment = GithubComment::create(&conf).unwrap();
+
+    // The repo file has no access lists, alice is still refused.
+    let mut cursor = ScanCursor::create(since());
+    comment
+        .scan_repo("https://github.com/owner/repo", &mut cursor)
+        .await
+        .unwrap();
+    assert!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/11/reactions")
+            .is_empty()
+    );
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/13/reactions")
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_github_comment_refresh_summary() {
+    let mock = MockGithub::start();
+    mock.fixture("GET", "/repos/owner/repo/pulls", "pulls.json")
+        .fixture(
+            "GET",
+            "/repos/owner/repo/issues/1/comments",
+            "summary_comments.json",
+        )
+        .respond("GET", "/user", 200, &[], r#"{"login": "askbend-bot"}"#);
+    let mut conf = mock_conf(&mock);
+    conf.github.auto_update_summary = true;
+    let comment = GithubComment::create(&conf).unwrap();
+
+    // The outdated summary fails without the PR diff, it's not retried for the same head sha.
+    let repo = "https://github.com/owner/repo";
+    let mut cursor = ScanCursor::create("2100-01-01T00:00:00Z".parse().unwrap());
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    let listed = mock
+        .requests("GET", "/repos/owner/repo/issues/1/comments")
+        .into_iter()
+        .filter(|x| !x.query.contains("since="))
+        .count();
+    assert_eq!(listed, 1);
+    assert_eq!(mock.requests("GET", "/repos/owner/repo/pulls/1").len(), 1);
+}
+
+#[tokio::test]
+async fn test_github_comment_scan_repo() {
+    let mock = MockGithub::start();
+    mock.fixture("GET", "/repos/owner/repo/pulls", "pulls.json")
+        .fixture(
+            "GET",
+            "/repos/owner/repo/issues/1/comments",
+            "comments.json",
+        )
+        .respond(
+            "POST",
+            "/repos/owner/repo/issues/comments/11/reactions",
+            201,
+            &[],
+            "{}",
+        );
+    let mut conf = mock_conf(&mock);
+    conf.github.repo_configs = vec![GithubRepoConfig {
+        repo: "https://github.com/owner/repo".to_string(),
+        commands: vec!["summary".to_string()],
+        ..Default::default()
+    }];
+    let comment = GithubComment::create(&conf).unwrap();
+
+    // The summary fails without the PR diff, the scan of the repo still succeeds.
+    let mut cursor = ScanCursor::create(since());
+    let repo = "https://github.com/owner/repo";
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    assert_eq!(
+        cursor.since(repo, 1),
+        "2023-07-01T04:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+    assert_eq!(
+        mock.requests("GET", "/repos/owner/repo/contents/.askbend.toml")
+            .len(),
+        1
+    );
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/11/reactions")
+            .len(),
+        1
+    );
+    assert!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/13/reactions")
+            .is_empty()
+    );
+    assert_eq!(mock.requests("GET", "/repos/owner/repo/pulls/1").len(), 1);
+
+    // The next scan lists the comments again, the command is not run twice.
+    comment.scan_repo(repo, &mut cursor).await.unwrap();
+    assert_eq!(
+        mock.requests("GET", "/repos/owner/repo/issues/1/comments")
+            .len(),
+        2
+    );
+    assert_eq!(
+        mock.requests("POST", "/repos/owner/repo/issues/comments/11/reactions")
+            .len(),
+        1
+    );
+    assert_eq!(mock.requests("GET", "/repos/owner/repo/pulls/1").len(), 1);
+
+    // The scan fails if the repo is not accessible.
+    assert!(
+        comment
+            .scan_repo("https://github.com/owner/missing", &mut cursor)
+            .await
+            .is_err()
+    );
+}
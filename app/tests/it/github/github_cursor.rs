@@ -0,0 +1,48 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::ScanCursor;
+use chrono::DateTime;
+use chrono::Utc;
+
+#[test]
+fn test_scan_cursor() {
+    let start: DateTime<Utc> = "2023-07-01T00:00:00Z".parse().unwrap();
+    let later: DateTime<Utc> = "2023-07-01T00:01:00Z".parse().unwrap();
+    let repo = "https://github.com/owner/repo";
+
+    let mut cursor = ScanCursor::create(start);
+    assert_eq!(cursor.since(repo, 1), start);
+    assert!(!cursor.is_new(repo, 1, 10, start));
+    assert!(cursor.is_new(repo, 1, 10, later));
+
+    cursor.advance(repo, 1, 10, later);
+    assert_eq!(cursor.since(repo, 1), later);
+    assert!(!cursor.is_new(repo, 1, 10, later));
+    // Another comment of the same second.
+    assert!(cursor.is_new(repo, 1, 11, later));
+    // Each PR has its own position.
+    assert_eq!(cursor.since(repo, 2), start);
+    assert_eq!(cursor.since("https://github.com/owner/other", 1), start);
+
+    // Never moves backwards.
+    cursor.advance(repo, 1, 9, start);
+    assert_eq!(cursor.since(repo, 1), later);
+    cursor.advance(repo, 1, 11, later);
+    assert!(!cursor.is_new(repo, 1, 11, later));
+
+    // The closed PRs are forgotten.
+    cursor.retain(repo, &[2]);
+    assert_eq!(cursor.since(repo, 1), start);
+}
@@ -0,0 +1,94 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::DiffLines;
+use askbend::GithubReview;
+use askbend::ReviewComment;
+use askbend::ReviewSeverity;
+
+#[test]
+fn test_diff_lines() {
+    let diff = "--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ mod a;
+-mod b;
++mod c;
++mod d;
+ mod e;
+--- a/src/old.rs
++++ b/src/old.rs
+@@ -10,2 +10,2 @@ fn main() {
+-    --x;
++    x -= 1;
+     x
+";
+    let lines = DiffLines::parse(diff);
+    assert!(lines.contains("src/lib.rs", 1));
+    assert!(lines.contains("src/lib.rs", 2));
+    assert!(lines.contains("src/lib.rs", 3));
+    assert!(lines.contains("src/lib.rs", 4));
+    assert!(!lines.contains("src/lib.rs", 5));
+    assert!(lines.contains("src/old.rs", 10));
+    assert!(lines.contains("src/old.rs", 11));
+    assert!(!lines.contains("src/old.rs", 12));
+    assert!(!lines.contains("src/main.rs", 1));
+
+    assert!(lines.annotated().contains("     2 +mod c;"));
+    assert!(lines.annotated().contains("       -mod b;"));
+}
+
+#[test]
+fn test_review_comment_parse() {
+    let text = "Here are the issues:
+[HIGH] src/lib.rs:12: possible overflow
+- [low] `src/main.rs`:3: rename the variable
+[MEDIUM] src/lib.rs: missing line number
+NONE";
+    let comments = ReviewComment::parse_list(text);
+    assert_eq!(comments, vec![
+        ReviewComment {
+            severity: ReviewSeverity::High,
+            path: "src/lib.rs".to_string(),
+            line: 12,
+            body: "possible overflow".to_string(),
+        },
+        ReviewComment {
+            severity: ReviewSeverity::Low,
+            path: "src/main.rs".to_string(),
+            line: 3,
+            body: "rename the variable".to_string(),
+        },
+    ]);
+}
+
+#[test]
+fn test_review_select() {
+    let comment = |severity, line| ReviewComment {
+        severity,
+        path: "a.rs".to_string(),
+        line,
+        body: "x".to_string(),
+    };
+    let comments = vec![
+        comment(ReviewSeverity::Low, 1),
+        comment(ReviewSeverity::Medium, 2),
+        comment(ReviewSeverity::High, 3),
+        comment(ReviewSeverity::Medium, 4),
+    ];
+
+    let selected = GithubReview::select(comments, ReviewSeverity::Medium, 2);
+    let lines = selected.iter().map(|x| x.line).collect::<Vec<_>>();
+    assert_eq!(lines, vec![3, 2]);
+}
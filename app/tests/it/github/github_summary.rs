@@ -0,0 +1,92 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::GithubSummary;
+use askbend::SummaryComment;
+use llmchain::Document;
+use llmchain::Documents;
+
+#[test]
+fn test_summary_comment() {
+    let sha = "3f2a1b0c9d8e7f6a5b4c3d2e1f0a9b8c7d6e5f4a";
+    let body = SummaryComment::render("* **Title**\nchanges", sha);
+    assert!(SummaryComment::is_summary(&body));
+    assert_eq!(SummaryComment::parse_sha(&body), Some(sha));
+    assert!(body.contains("_Summary of commit 3f2a1b0._"));
+
+    let legacy = "## PR Summary(By [llmchain.rs](https://github.com/shafishlabs/llmchain.rs)):\nxx";
+    assert!(!SummaryComment::is_summary(legacy));
+    assert_eq!(SummaryComment::parse_sha(legacy), None);
+    assert_eq!(SummaryComment::parse_sha("<!-- askbend:summary -->"), None);
+}
+
+#[test]
+fn test_summary_group_patches() {
+    let patch = |path: &str| {
+        Document::create(
+            "a/b/pull/1",
+            &format!("--- a/{}\n+++ b/{}\n@@ -1,1 +1,1 @@\n-x\n+y", path, path),
+        )
+    };
+    let patches = Documents::from(vec![
+        patch("README.md"),
+        patch("src/query/service/a.rs"),
+        patch("src/query/sql/b.rs"),
+        patch("src/common/c.rs"),
+        patch("src/lib.rs"),
+    ]);
+
+    let groups = GithubSummary::group_patches(&patches, 2)
+        .into_iter()
+        .map(|(group, patches)| (group, patches.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(groups, vec![
+        ("src/query".to_string(), 2),
+        ("/".to_string(), 1),
+        ("src".to_string(), 1),
+        ("src/common".to_string(), 1),
+    ]);
+
+    let groups = GithubSummary::group_patches(&patches, 1)
+        .into_iter()
+        .map(|(group, patches)| (group, patches.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(groups, vec![("src".to_string(), 4), ("/".to_string(), 1)]);
+}
+
+#[test]
+fn test_summary_reduce_text() {
+    let summaries = vec![
+        ("src/query".to_string(), "Add the query cache.".to_string()),
+        ("src/meta".to_string(), "word ".repeat(200)),
+        ("docs".to_string(), "Update the docs.".to_string()),
+    ];
+
+    let (text, left_out) = GithubSummary::reduce_text(&summaries, 10000).unwrap();
+    assert!(text.contains("Changes in `docs`:\nUpdate the docs."));
+    assert!(left_out.is_empty());
+
+    // The group overflowing is truncated, the rest are left out.
+    let (text, left_out) = GithubSummary::reduce_text(&summaries, 100).unwrap();
+    assert!(text.starts_with(
+        "Changes in `src/query`:\nAdd the query cache.\nChanges in `src/meta`:\nword"
+    ));
+    assert!(text.len() < summaries[1].1.len());
+    assert_eq!(left_out, vec!["docs".to_string()]);
+
+    // No room for the truncated group.
+    let (text, left_out) = GithubSummary::reduce_text(&summaries, 20).unwrap();
+    assert_eq!(text, "Changes in `src/query`:\nAdd the query cache.");
+    assert_eq!(left_out, vec!["src/meta".to_string(), "docs".to_string()]);
+}
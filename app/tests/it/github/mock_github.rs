@@ -0,0 +1,158 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use actix_web::http::StatusCode;
+use actix_web::web;
+use actix_web::App;
+use actix_web::HttpRequest;
+use actix_web::HttpResponse;
+use actix_web::HttpServer;
+
+/// A request received by the mock server.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    // (method, path) -> responses, the last one is replayed for the following requests.
+    routes: Mutex<HashMap<(String, String), VecDeque<MockResponse>>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+/// A local GitHub API server replaying the fixtures, unknown routes respond 404.
+pub struct MockGithub {
+    pub url: String,
+    state: Arc<MockState>,
+}
+
+impl MockGithub {
+    pub fn start() -> Self {
+        let state = Arc::new(MockState::default());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let data = web::Data::from(state.clone());
+        std::thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                HttpServer::new(move || {
+                    App::new()
+                        .app_data(data.clone())
+                        .default_service(web::to(handle))
+                })
+                .workers(1)
+                .listen(listener)
+                .unwrap()
+                .run()
+                .await
+            })
+        });
+
+        MockGithub { url, state }
+    }
+
+    /// Respond the method and path with the fixture file in `tests/testdata/github`.
+    pub fn fixture(&self, method: &str, path: &str, file: &str) -> &Self {
+        let body = std::fs::read_to_string(format!("tests/testdata/github/{}", file)).unwrap();
+        self.respond(method, path, 200, &[], &body)
+    }
+
+    pub fn respond(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> &Self {
+        let response = MockResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_string(),
+        };
+        self.state
+            .routes
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), path.to_string()))
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    pub fn requests(&self, method: &str, path: &str) -> Vec<MockRequest> {
+        self.state
+            .requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|x| x.method == method && x.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+async fn handle(req: HttpRequest, body: web::Bytes, state: web::Data<MockState>) -> HttpResponse {
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+    state.requests.lock().unwrap().push(MockRequest {
+        method: method.clone(),
+        path: path.clone(),
+        query: req.query_string().to_string(),
+        body: String::from_utf8_lossy(&body).to_string(),
+    });
+
+    let response = {
+        let mut routes = state.routes.lock().unwrap();
+        routes.get_mut(&(method, path)).and_then(|responses| {
+            if responses.len() > 1 {
+                responses.pop_front()
+            } else {
+                responses.front().cloned()
+            }
+        })
+    };
+    match response {
+        Some(response) => {
+            let mut builder = HttpResponse::build(StatusCode::from_u16(response.status).unwrap());
+            for (k, v) in response.headers {
+                builder.insert_header((k, v));
+            }
+            builder.content_type("application/json").body(response.body)
+        }
+        None => HttpResponse::NotFound()
+            .content_type("application/json")
+            .body(r#"{"message":"Not Found"}"#),
+    }
+}
@@ -12,4 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod api;
 mod base;
+mod configs;
+mod github;
+mod qa;
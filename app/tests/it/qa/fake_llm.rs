@@ -0,0 +1,56 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use llmchain::EmbeddingResult;
+use llmchain::GenerateResult;
+use llmchain::LLM;
+
+/// LLM replying the same generation, the prompts are recorded.
+pub struct FakeLLM {
+    pub generation: String,
+    pub prompts: Mutex<Vec<String>>,
+}
+
+impl FakeLLM {
+    pub fn create(generation: &str) -> Arc<Self> {
+        Arc::new(FakeLLM {
+            generation: generation.to_string(),
+            prompts: Mutex::new(vec![]),
+        })
+    }
+
+    pub fn prompts(&self) -> Vec<String> {
+        self.prompts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl LLM for FakeLLM {
+    async fn embedding(&self, _inputs: Vec<String>) -> Result<EmbeddingResult> {
+        Err(anyhow!("FakeLLM has no embeddings"))
+    }
+
+    async fn generate(&self, input: &str) -> Result<GenerateResult> {
+        self.prompts.lock().unwrap().push(input.to_string());
+        Ok(GenerateResult {
+            generation: self.generation.clone(),
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QAChunk;
+
+mod fake_llm;
+mod qa_checkpoint;
+mod qa_code_splitter;
+mod qa_context;
+mod qa_corpus_stats;
+mod qa_dedup;
+mod qa_eval;
+mod qa_explain;
+mod qa_federated_search;
+mod qa_git_source;
+mod qa_index_meta;
+mod qa_loader;
+mod qa_markdown_splitter;
+mod qa_query_rewriter;
+mod qa_reranker;
+mod qa_search;
+mod qa_text_splitter;
+
+/// A chunk of the collection retrieved with the score.
+pub fn chunk(collection: &str, path: &str, score: f32) -> QAChunk {
+    QAChunk {
+        collection: collection.to_string(),
+        score,
+        ..QAChunk::create(path, "text")
+    }
+}
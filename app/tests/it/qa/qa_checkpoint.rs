@@ -0,0 +1,66 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QACheckpoint;
+use askbend::QAChunk;
+
+#[test]
+fn test_qa_checkpoint() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data/checkpoint.json");
+
+    let chunks = vec![
+        QAChunk::create("/a.md", "COPY INTO loads data from a stage."),
+        QAChunk::create("/b.md", "SELECT 1"),
+    ];
+    let fingerprint = QACheckpoint::fingerprint("askbend.doc", 100, &chunks);
+    assert_eq!(
+        fingerprint,
+        QACheckpoint::fingerprint("askbend.doc", 100, &chunks)
+    );
+    assert_ne!(
+        fingerprint,
+        QACheckpoint::fingerprint("askbend.doc", 1, &chunks)
+    );
+    assert_ne!(
+        fingerprint,
+        QACheckpoint::fingerprint("askbend.doc", 100, &chunks[..1])
+    );
+
+    let mut checkpoint = QACheckpoint::load(&path, &fingerprint).unwrap();
+    assert!(!checkpoint.is_resumed());
+    checkpoint.mark_done(0).unwrap();
+    checkpoint.mark_done(2).unwrap();
+
+    // Resume the same rebuild.
+    let checkpoint = QACheckpoint::load(&path, &fingerprint).unwrap();
+    assert!(checkpoint.is_resumed());
+    assert!(checkpoint.is_done(0));
+    assert!(!checkpoint.is_done(1));
+    assert!(checkpoint.is_done(2));
+
+    // Another rebuild starts over.
+    let other = QACheckpoint::load(&path, "other").unwrap();
+    assert!(!other.is_resumed());
+
+    checkpoint.remove().unwrap();
+    assert!(!path.exists());
+    let checkpoint = QACheckpoint::load(&path, &fingerprint).unwrap();
+    assert!(!checkpoint.is_resumed());
+
+    // A broken checkpoint is ignored.
+    std::fs::write(&path, "{").unwrap();
+    let checkpoint = QACheckpoint::load(&path, &fingerprint).unwrap();
+    assert!(!checkpoint.is_resumed());
+}
@@ -0,0 +1,91 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QACodeSplitter;
+use llmchain::Document;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+
+fn split(splitter: &QACodeSplitter, path: &str, content: &str) -> Vec<String> {
+    let documents = Documents::from(vec![Document::create(path, content)]);
+    splitter
+        .split_documents(&documents)
+        .unwrap()
+        .iter()
+        .map(|x| x.content)
+        .collect()
+}
+
+#[test]
+fn test_qa_code_splitter() {
+    let code = r#"use std::fmt;
+
+/// Add two numbers.
+#[inline]
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn sub(a: i32, b: i32) -> i32 {
+    a - b
+}
+
+pub struct Point {
+    x: i32,
+}"#;
+    let splitter = QACodeSplitter::create().with_chunk_size(80);
+    let chunks = split(&splitter, "lib.rs", code);
+    assert_eq!(chunks, vec![
+        "use std::fmt;\n",
+        "/// Add two numbers.\n#[inline]\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n",
+        "fn sub(a: i32, b: i32) -> i32 {\n    a - b\n}\n\npub struct Point {\n    x: i32,\n}",
+    ]);
+
+    // The small definitions are merged.
+    let splitter = QACodeSplitter::create();
+    let chunks = split(&splitter, "lib.rs", code);
+    assert_eq!(chunks, vec![code.to_string()]);
+
+    // The large definition is split by lines.
+    let splitter = QACodeSplitter::create().with_chunk_size(20);
+    let chunks = split(
+        &splitter,
+        "lib.rs",
+        "fn main() {\n    let a = 1;\n    let b = 2;\n}",
+    );
+    assert_eq!(chunks, vec![
+        "fn main() {",
+        "    let a = 1;",
+        "    let b = 2;\n}"
+    ]);
+}
+
+#[test]
+fn test_qa_code_splitter_sql() {
+    let sql = r#"-- Create the table
+CREATE TABLE t (
+    a INT
+);
+
+-- Insert the rows
+INSERT INTO t VALUES (1), (2);
+SELECT * FROM t;"#;
+    let splitter = QACodeSplitter::create().with_chunk_size(60);
+    let chunks = split(&splitter, "example.SQL", sql);
+    assert_eq!(chunks, vec![
+        "-- Create the table\nCREATE TABLE t (\n    a INT\n);\n",
+        "-- Insert the rows\nINSERT INTO t VALUES (1), (2);",
+        "SELECT * FROM t;",
+    ]);
+}
@@ -0,0 +1,221 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use askbend::QAChunk;
+use askbend::QAContextBuilder;
+use askbend::QAContextOverflow;
+
+use crate::qa::chunk;
+use crate::qa::fake_llm::FakeLLM;
+
+/// The chunk at the index of the document, with the words of content.
+fn words_chunk(path: &str, index: usize, words: usize) -> QAChunk {
+    QAChunk {
+        index,
+        content: vec!["hello"; words].join(" "),
+        ..chunk("", path, 0.0)
+    }
+}
+
+fn content(chunk: &QAChunk) -> String {
+    chunk.content.clone()
+}
+
+#[test]
+fn test_qa_context_overflow() {
+    assert_eq!(
+        QAContextOverflow::from_str("truncate").unwrap(),
+        QAContextOverflow::Truncate
+    );
+    assert_eq!(
+        QAContextOverflow::from_str("Summarize").unwrap(),
+        QAContextOverflow::Summarize
+    );
+    assert!(QAContextOverflow::from_str("cut").is_err());
+}
+
+#[tokio::test]
+async fn test_qa_context_build_within_budget() {
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 200).unwrap();
+    let chunks = vec![words_chunk("/a.md", 0, 50), words_chunk("/b.md", 0, 50)];
+    let context = builder.build(chunks, content).await.unwrap();
+    assert_eq!(context.chunks.len(), 2);
+    assert_eq!(context.tokens, 100);
+    assert_eq!(context.shortened, 0);
+    assert_eq!(context.dropped, 0);
+}
+
+#[tokio::test]
+async fn test_qa_context_build_shorter_format() {
+    // The formatted text takes fewer tokens than the content.
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 2000)
+        .unwrap()
+        .with_max_tokens(100);
+    let half = |x: &QAChunk| {
+        let words = x.content.split(' ').collect::<Vec<_>>();
+        words[..words.len() / 2].join(" ")
+    };
+    let context = builder
+        .build(vec![words_chunk("/a.md", 0, 300)], half)
+        .await
+        .unwrap();
+    assert_eq!(context.chunks.len(), 1);
+    assert_eq!(context.tokens, 50);
+    assert_eq!(context.shortened, 1);
+}
+
+#[tokio::test]
+async fn test_qa_context_build_truncate() {
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 200).unwrap();
+    let chunks = vec![
+        words_chunk("/a.md", 0, 120),
+        words_chunk("/b.md", 0, 120),
+        words_chunk("/c.md", 0, 10),
+    ];
+    let context = builder.build(chunks, content).await.unwrap();
+    // The second chunk is truncated to the budget left, the third one is dropped.
+    let paths = context
+        .chunks
+        .iter()
+        .map(|x| x.path.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec!["/a.md", "/b.md"]);
+    assert_eq!(context.tokens, 200);
+    assert_eq!(builder.tokens(&context.chunks[1].content), 80);
+    assert_eq!(context.shortened, 1);
+    assert_eq!(context.dropped, 1);
+}
+
+#[tokio::test]
+async fn test_qa_context_build_drop() {
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 200).unwrap();
+    let chunks = vec![words_chunk("/a.md", 0, 190), words_chunk("/b.md", 0, 100)];
+    let context = builder.build(chunks, content).await.unwrap();
+    // Too little budget left to shorten the second chunk.
+    assert_eq!(context.chunks.len(), 1);
+    assert_eq!(context.shortened, 0);
+    assert_eq!(context.dropped, 1);
+}
+
+#[tokio::test]
+async fn test_qa_context_build_summarize() {
+    let llm = FakeLLM::create("a short summary");
+    let builder = QAContextBuilder::create(llm.clone(), 200)
+        .unwrap()
+        .with_overflow(QAContextOverflow::Summarize);
+    let chunks = vec![words_chunk("/a.md", 0, 120), words_chunk("/b.md", 0, 120)];
+    let context = builder.build(chunks, content).await.unwrap();
+    assert_eq!(context.chunks[0].content.len(), 120 * 6 - 1);
+    assert_eq!(context.chunks[1].content, "a short summary");
+    assert_eq!(context.shortened, 1);
+
+    let prompts = llm.prompts();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("at most 60 words"));
+}
+
+#[tokio::test]
+async fn test_qa_context_expand() {
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 100).unwrap();
+    let chunks = vec![words_chunk("/a.md", 3, 20), words_chunk("/b.md", 0, 20)];
+    let mut context = builder.build(chunks, content).await.unwrap();
+
+    let neighbors = vec![
+        QAChunk {
+            index: 1,
+            ..QAChunk::create("/a.md", "first")
+        },
+        QAChunk {
+            index: 2,
+            ..QAChunk::create("/a.md", "before")
+        },
+        QAChunk {
+            index: 4,
+            ..QAChunk::create("/a.md", &vec!["after"; 60].join(" "))
+        },
+        QAChunk {
+            index: 1,
+            ..QAChunk::create("/b.md", "next")
+        },
+    ];
+    builder.expand(&mut context, &neighbors, content);
+
+    // The long neighbor is over the budget, the others are merged in the document order.
+    assert_eq!(
+        context.chunks[0].content,
+        format!("first\nbefore\n{}", vec!["hello"; 20].join(" "))
+    );
+    assert_eq!(
+        context.chunks[1].content,
+        format!("{}\nnext", vec!["hello"; 20].join(" "))
+    );
+    assert_eq!(context.expanded, 3);
+    assert_eq!(context.contexts[0], context.chunks[0].content);
+    assert!(context.tokens <= 100);
+}
+
+#[tokio::test]
+async fn test_qa_context_expand_sections() {
+    let section_chunk = |path: &str, index: usize, section_id: usize, content: &str| QAChunk {
+        index,
+        section_id,
+        ..QAChunk::create(path, content)
+    };
+    let builder = QAContextBuilder::create(FakeLLM::create(""), 100).unwrap();
+    let chunks = vec![
+        section_chunk("/a.md", 2, 1, "hit"),
+        section_chunk("/b.md", 0, 0, &vec!["hello"; 20].join(" ")),
+        section_chunk("/a.md", 3, 1, "second hit"),
+    ];
+    let mut context = builder.build(chunks, content).await.unwrap();
+
+    let sections = vec![
+        section_chunk("/a.md", 1, 1, "setup"),
+        section_chunk("/a.md", 2, 1, "hit"),
+        section_chunk("/a.md", 3, 1, "second hit"),
+        section_chunk("/b.md", 0, 0, &vec!["hello"; 20].join(" ")),
+        section_chunk("/b.md", 1, 0, &vec!["world"; 80].join(" ")),
+    ];
+    builder.expand_sections(&mut context, &sections, content);
+
+    // The lower ranked hit of the section merged is removed, the long section is over the budget.
+    let contents = context
+        .chunks
+        .iter()
+        .map(|x| x.content.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(contents, vec![
+        "setup\nhit\nsecond hit",
+        &vec!["hello"; 20].join(" ")
+    ]);
+    assert_eq!(context.positions, vec![vec![1, 2, 3], vec![0]]);
+    assert_eq!(context.expanded, 1);
+    assert_eq!(context.contexts.len(), 2);
+    assert_eq!(
+        context.tokens,
+        context
+            .contexts
+            .iter()
+            .map(|x| builder.tokens(x))
+            .sum::<usize>()
+    );
+
+    // The neighbors are merged next to the whole section.
+    let neighbors = vec![section_chunk("/a.md", 0, 0, "# Title")];
+    builder.expand(&mut context, &neighbors, content);
+    assert_eq!(context.chunks[0].content, "# Title\nsetup\nhit\nsecond hit");
+    assert_eq!(context.expanded, 2);
+}
@@ -0,0 +1,102 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::QAChunk;
+use askbend::QACorpusStats;
+use askbend::QADeduplicator;
+use askbend::QAEmbedding;
+use llmchain::Document;
+use llmchain::Documents;
+
+#[test]
+fn test_qa_corpus_stats() {
+    let documents = Documents::from(vec![
+        Document::create("/a.md", "a"),
+        Document::create("/b.md", "b"),
+    ]);
+    let chunks = vec![
+        QAChunk::create("/a.md", "Licensed under the Apache License."),
+        QAChunk::create("/a.md", "COPY INTO loads data from a stage into a table."),
+        QAChunk::create("/b.md", "Licensed under the Apache License.\n"),
+        QAChunk::create("/b.md", "ok"),
+    ];
+    let skipped = vec!["/logo.png".to_string()];
+    let stats = QACorpusStats::create(&documents, &chunks, &skipped).unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.chunks, 4);
+    assert_eq!(stats.skipped_files, skipped);
+    assert!(stats.tokens > 0);
+    assert!(stats.embedding_cost > 0.0);
+    assert_eq!(
+        stats.largest_chunks[0].tokens,
+        stats.largest_chunks.iter().map(|x| x.tokens).max().unwrap()
+    );
+    assert_eq!(stats.smallest_chunks[0].path, "/b.md");
+    assert_eq!(stats.duplicate_chunks, 1);
+    assert_eq!(stats.duplicates[0].paths, vec!["/a.md", "/b.md"]);
+    assert_eq!(
+        stats.duplicates[0].preview,
+        "Licensed under the Apache License."
+    );
+
+    let report = stats.report();
+    assert!(report.contains("chunks: 4"));
+    assert!(report.contains("duplicate chunks: 1"));
+    assert!(report.contains("/logo.png"));
+}
+
+#[test]
+fn test_qa_corpus_stats_merged() {
+    let documents = Documents::from(vec![
+        Document::create("/a.md", "a"),
+        Document::create("/b.md", "b"),
+    ]);
+    let chunks = vec![
+        QAChunk::create("/a.md", "Licensed under the Apache License."),
+        QAChunk::create("/b.md", "Licensed under the Apache License."),
+        QAChunk::create("/b.md", "COPY INTO loads data from a stage into a table."),
+    ];
+    let (deduped, groups) = QADeduplicator::create(&Config::default().qa)
+        .unwrap()
+        .dedup(chunks.clone());
+    let stats = QACorpusStats::create(&documents, &deduped, &[])
+        .unwrap()
+        .with_duplicates(&chunks)
+        .with_merged(&groups);
+
+    // The chunks to embed are counted after the deduplication, the duplicates before it.
+    assert_eq!(stats.chunks, 2);
+    assert_eq!(stats.duplicate_chunks, 1);
+    assert_eq!(stats.duplicates[0].paths, vec!["/a.md", "/b.md"]);
+    assert_eq!(stats.merged_chunks, 1);
+    assert_eq!(stats.merged[0].paths, vec!["/a.md", "/b.md"]);
+}
+
+#[tokio::test]
+async fn test_qa_embedding_dry_run() {
+    let mut conf = Config::default();
+    let root = std::fs::canonicalize("tests/testdata").unwrap();
+    conf.qa.path = format!("{}/", root.display());
+    conf.qa.ignored_paths = vec!["**/ignore_dir/**".to_string()];
+    // No dsn, nothing is written to Databend.
+    let stats = QAEmbedding::create(&conf).dry_run().await.unwrap();
+
+    assert_eq!(stats.files, 3);
+    assert!(stats.chunks >= stats.files);
+    // The files matching no loader are not walked.
+    assert!(!stats.skipped_files.iter().any(|x| x.ends_with("rust.rs")));
+    assert!(stats.skipped_files.iter().any(|x| x.ends_with("3.md")));
+}
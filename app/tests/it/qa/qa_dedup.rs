@@ -0,0 +1,140 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use askbend::Config;
+use askbend::QAChunk;
+use askbend::QADedupMode;
+use askbend::QADeduplicator;
+
+const SQL_EXAMPLE: &str = "CREATE TABLE books (id BIGINT UNSIGNED, title VARCHAR, author VARCHAR, date VARCHAR); \
+    COPY INTO books FROM 'https://datafuse-1253727613.cos.ap-hongkong.myqcloud.com/data/books.parquet' \
+    FILE_FORMAT = (type = PARQUET); SELECT * FROM books LIMIT 10;";
+
+fn paths(chunks: &[QAChunk]) -> Vec<&str> {
+    chunks.iter().map(|x| x.path.as_str()).collect()
+}
+
+#[test]
+fn test_qa_dedup_exact() {
+    let mut conf = Config::default();
+    conf.qa.dedup = "exact".to_string();
+    let deduplicator = QADeduplicator::create(&conf.qa).unwrap();
+
+    let chunks = vec![
+        QAChunk::create("/a.md", "Licensed under the Apache License, Version 2.0."),
+        QAChunk::create("/a.md", "COPY INTO loads data from a stage."),
+        QAChunk::create("/b.md", "licensed under the  Apache License Version 2.0\n"),
+        QAChunk::create("/c.md", "Licensed under the Apache License, Version 2.1."),
+    ];
+    let (kept, groups) = deduplicator.dedup(chunks);
+    assert_eq!(paths(&kept), vec!["/a.md", "/a.md", "/c.md"]);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].kept.path, "/a.md");
+    assert_eq!(paths(&groups[0].removed), vec!["/b.md"]);
+}
+
+#[test]
+fn test_qa_dedup_near() {
+    let conf = Config::default();
+    let deduplicator = QADeduplicator::create(&conf.qa).unwrap();
+
+    let near = SQL_EXAMPLE.replace("LIMIT 10", "LIMIT 10 ");
+    let near = near
+        .replace("books LIMIT", "books  LIMIT")
+        .replace(';', ";\n");
+    let changed = SQL_EXAMPLE.replace("LIMIT 10", "WHERE author = 'Tom' ORDER BY date LIMIT 5");
+    let chunks = vec![
+        QAChunk::create("/docs/v1.0/load.md", SQL_EXAMPLE),
+        QAChunk::create("/docs/load.md", &near),
+        QAChunk::create("/docs/query.md", &changed),
+        QAChunk::create("/docs/other.md", "Something else entirely."),
+    ];
+    let (kept, groups) = deduplicator.dedup(chunks.clone());
+    assert_eq!(paths(&kept), vec![
+        "/docs/v1.0/load.md",
+        "/docs/query.md",
+        "/docs/other.md"
+    ]);
+    assert_eq!(groups.len(), 1);
+
+    // Near duplicates with a small change.
+    let mut words = SQL_EXAMPLE.split(' ').collect::<Vec<_>>();
+    let last = words.len() - 1;
+    words[last] = "100;";
+    let almost = words.join(" ");
+    let (kept, _) = deduplicator.dedup(vec![
+        QAChunk::create("/a.md", SQL_EXAMPLE),
+        QAChunk::create("/b.md", &almost),
+    ]);
+    assert_eq!(paths(&kept), vec!["/a.md"]);
+
+    // The similarity threshold.
+    let mut conf = Config::default();
+    conf.qa.dedup_similarity = 100;
+    let (kept, _) = QADeduplicator::create(&conf.qa).unwrap().dedup(vec![
+        QAChunk::create("/a.md", SQL_EXAMPLE),
+        QAChunk::create("/b.md", &almost),
+    ]);
+    assert_eq!(paths(&kept), vec!["/a.md", "/b.md"]);
+
+    // Off.
+    conf.qa.dedup = "off".to_string();
+    let (kept, groups) = QADeduplicator::create(&conf.qa).unwrap().dedup(chunks);
+    assert_eq!(kept.len(), 4);
+    assert!(groups.is_empty());
+}
+
+#[test]
+fn test_qa_dedup_keep() {
+    let chunks = vec![
+        QAChunk::create("/docs/v1.0/load.md", SQL_EXAMPLE),
+        QAChunk::create("/docs/load.md", SQL_EXAMPLE),
+        QAChunk::create("/blog/load.md", SQL_EXAMPLE),
+    ];
+
+    let mut conf = Config::default();
+    conf.qa.dedup_keep = "shortest_path".to_string();
+    let (kept, groups) = QADeduplicator::create(&conf.qa)
+        .unwrap()
+        .dedup(chunks.clone());
+    assert_eq!(paths(&kept), vec!["/docs/load.md"]);
+    assert_eq!(paths(&groups[0].removed), vec![
+        "/docs/v1.0/load.md",
+        "/blog/load.md"
+    ]);
+
+    // The preferred paths win over the keep rule.
+    conf.qa.dedup_prefer_paths = vec!["/blog/**".to_string()];
+    let (kept, _) = QADeduplicator::create(&conf.qa).unwrap().dedup(chunks);
+    assert_eq!(paths(&kept), vec!["/blog/load.md"]);
+}
+
+#[test]
+fn test_qa_dedup_config() {
+    assert_eq!(QADedupMode::from_str("Near").unwrap(), QADedupMode::Near);
+
+    let mut conf = Config::default();
+    conf.qa.dedup = "fuzzy".to_string();
+    assert!(QADeduplicator::create(&conf.qa).is_err());
+
+    let mut conf = Config::default();
+    conf.qa.dedup_keep = "last".to_string();
+    assert!(QADeduplicator::create(&conf.qa).is_err());
+
+    let mut conf = Config::default();
+    conf.qa.dedup_similarity = 0;
+    assert!(QADeduplicator::create(&conf.qa).is_err());
+}
@@ -0,0 +1,225 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use askbend::Config;
+use askbend::QAAnswer;
+use askbend::QAChunk;
+use askbend::QAEval;
+use askbend::QAEvalCase;
+use askbend::QAEvalLLM;
+use askbend::QAEvalReport;
+use askbend::QAEvalResult;
+use askbend::QAExplain;
+use askbend::QAExtractiveLLM;
+use llmchain::DocumentRetrievalPrompt;
+use llmchain::Prompt;
+use llmchain::LLM;
+
+use crate::qa::chunk;
+
+fn answer(answer: &str, chunks: Vec<QAChunk>, retrieved: Vec<QAChunk>) -> QAAnswer {
+    QAAnswer {
+        answer: answer.to_string(),
+        explain: QAExplain {
+            chunks,
+            retrieved,
+            ..Default::default()
+        },
+    }
+}
+
+fn case(question: &str, sources: &[&str], facts: &[&str], no_answer: bool) -> QAEvalCase {
+    QAEvalCase {
+        question: question.to_string(),
+        sources: sources.iter().map(|x| x.to_string()).collect(),
+        facts: facts.iter().map(|x| x.to_string()).collect(),
+        no_answer,
+    }
+}
+
+#[tokio::test]
+async fn test_qa_extractive_llm() {
+    let mut input_variables = HashMap::new();
+    input_variables.insert("question", "how to copy?");
+    input_variables.insert(
+        "contexts",
+        "context:COPY INTO t FROM @stage\nsource:copy.md\n",
+    );
+    let prompt = DocumentRetrievalPrompt::create()
+        .format(input_variables)
+        .unwrap();
+
+    let llm = QAExtractiveLLM::create();
+    let result = llm.generate(&prompt).await.unwrap();
+    assert_eq!(
+        result.generation,
+        "context:COPY INTO t FROM @stage\nsource:copy.md"
+    );
+    let result = llm.generate("Rewrite the question").await.unwrap();
+    assert_eq!(result.generation, "");
+
+    assert_eq!(
+        QAEvalLLM::from_str("extractive").unwrap(),
+        QAEvalLLM::Extractive
+    );
+    assert!(QAEvalLLM::from_str("fake").is_err());
+}
+
+#[test]
+fn test_qa_eval_load_cases() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("golden.jsonl");
+    let path = path.to_str().unwrap();
+
+    std::fs::write(
+        path,
+        r#"{"question": "how to copy?", "sources": ["load/copy.md"], "facts": ["COPY INTO"]}
+
+{"question": "whats the weather?", "no_answer": true}
+"#,
+    )
+    .unwrap();
+    let cases = QAEval::load_cases(path).unwrap();
+    assert_eq!(cases, vec![
+        case("how to copy?", &["load/copy.md"], &["COPY INTO"], false),
+        case("whats the weather?", &[], &[], true),
+    ]);
+
+    std::fs::write(path, "{\"question\": \"ok\"}\n{\"question\": 1}\n").unwrap();
+    let err = QAEval::load_cases(path).unwrap_err();
+    assert!(err.to_string().contains("golden.jsonl:2"));
+}
+
+#[test]
+fn test_qa_eval_create() {
+    let mut conf = Config::default();
+    conf.qa.answer_table = "answer".to_string();
+    conf.qa.eval_llm = "extractive".to_string();
+
+    // The eval answers are not logged into the answer table.
+    let eval = QAEval::create(&conf).unwrap();
+    assert_eq!(eval.conf().qa.answer_table, "");
+    assert_eq!(eval.conf().qa.eval_llm, "extractive");
+
+    conf.qa.eval_llm = "gpt".to_string();
+    assert!(QAEval::create(&conf).is_err());
+}
+
+#[test]
+fn test_qa_eval_result() {
+    let golden = case(
+        "how to copy csv?",
+        &["load/copy.md", "/load/csv"],
+        &["COPY INTO", "file_format = (type = csv)"],
+        false,
+    );
+    let result = QAEvalResult::create(
+        &golden,
+        Ok(answer(
+            "Use COPY INTO t FROM @s FILE_FORMAT = (TYPE =  CSV).",
+            vec![
+                chunk("default", "/docs/load/stage.md", 0.0),
+                chunk("default", "/docs/load/copy.md", 0.0),
+            ],
+            vec![chunk("default", "/docs/load/copy.md", 0.0), QAChunk {
+                slug: "/load/csv".to_string(),
+                ..chunk("default", "/docs/load/csv.md", 0.0)
+            }],
+        )),
+        2,
+    );
+    assert_eq!(result.retrieved, vec![
+        "/docs/load/stage.md",
+        "/docs/load/copy.md",
+        "/load/csv"
+    ]);
+    // The csv page is out of the top 2.
+    assert_eq!(result.recall, 0.5);
+    assert_eq!(result.reciprocal_rank, 0.5);
+    assert_eq!(result.facts_found.len(), 2);
+    assert!(result.facts_missing.is_empty());
+    assert!(!result.no_answer);
+
+    let golden = case("whats the weather?", &[], &["sunny"], true);
+    let result = QAEvalResult::create(&golden, Ok(answer("I don't know.", vec![], vec![])), 2);
+    assert!(result.no_answer);
+    assert_eq!(result.facts_missing, vec!["sunny"]);
+
+    let result = QAEvalResult::create(&golden, Ok(answer(QAAnswer::FALLBACK, vec![], vec![])), 2);
+    assert!(result.no_answer);
+    // An apology with an answer is not declining.
+    let result = QAEvalResult::create(
+        &golden,
+        Ok(answer(
+            "Sorry for the confusion, it's sunny.",
+            vec![],
+            vec![],
+        )),
+        2,
+    );
+    assert!(!result.no_answer);
+
+    let result = QAEvalResult::create(&golden, Err(anyhow!("timeout")), 2);
+    assert_eq!(result.error, Some("timeout".to_string()));
+}
+
+#[test]
+fn test_qa_eval_report() {
+    let results = vec![
+        QAEvalResult::create(
+            &case("q1", &["a.md"], &["x", "y"], false),
+            Ok(answer("x", vec![chunk("default", "/a.md", 0.0)], vec![])),
+            2,
+        ),
+        QAEvalResult::create(
+            &case("q2", &["b.md"], &[], false),
+            Ok(answer(
+                "Sorry, I dont know",
+                vec![
+                    chunk("default", "/c.md", 0.0),
+                    chunk("default", "/b.md", 0.0),
+                ],
+                vec![],
+            )),
+            2,
+        ),
+        QAEvalResult::create(
+            &case("q3", &[], &[], true),
+            Ok(answer("", vec![], vec![])),
+            2,
+        ),
+        QAEvalResult::create(
+            &case("q4", &["d.md"], &["z"], false),
+            Err(anyhow!("timeout")),
+            2,
+        ),
+    ];
+    let report = QAEvalReport::create(2, results);
+    assert_eq!(report.questions, 4);
+    assert_eq!(report.errors, 1);
+    assert_eq!(report.recall_at_k, 0.6667);
+    assert_eq!(report.mrr, 0.5);
+    assert_eq!(report.fact_coverage, 0.25);
+    assert_eq!(report.no_answers, 2);
+    assert_eq!(report.no_answer_precision, Some(0.5));
+    assert!(report.summary().contains("recall@2: 0.6667"));
+
+    let json = report.to_json().unwrap();
+    let parsed: QAEvalReport = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, report);
+}
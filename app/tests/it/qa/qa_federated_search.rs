@@ -0,0 +1,68 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QAChunk;
+use askbend::QAFederatedSearch;
+use askbend::QALLM;
+
+use crate::qa::chunk;
+
+#[test]
+fn test_qa_federated_search_score() {
+    assert_eq!(QAFederatedSearch::score(1.0, 0.5, 100), 1.0);
+    assert_eq!(QAFederatedSearch::score(0.75, 0.5, 100), 0.5);
+    assert_eq!(QAFederatedSearch::score(0.75, 0.5, 50), 0.25);
+    assert_eq!(QAFederatedSearch::score(0.4, 0.5, 100), 0.0);
+}
+
+#[test]
+fn test_qa_federated_search_merge() {
+    let docs = vec![
+        chunk("default", "/docs/copy.md", 0.9),
+        chunk("default", "/docs/stage.md", 0.4),
+    ];
+    let runbooks = vec![
+        chunk("runbooks", "/runbooks/restart.md", 0.6),
+        chunk("runbooks", "/runbooks/oncall.md", 0.4),
+    ];
+    let merged = QAFederatedSearch::merge(vec![docs, runbooks], 3);
+    let merged = merged
+        .iter()
+        .map(|x| (x.collection.as_str(), x.path.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(merged, vec![
+        ("default", "/docs/copy.md"),
+        ("runbooks", "/runbooks/restart.md"),
+        // The first collection wins the tie.
+        ("default", "/docs/stage.md"),
+    ]);
+}
+
+#[test]
+fn test_qa_federated_context_label() {
+    let chunk = QAChunk {
+        title: "Restart".to_string(),
+        ..chunk("runbooks", "/runbooks/restart.md", 0.6)
+    };
+    assert_eq!(
+        QALLM::format_context(&chunk, ""),
+        "collection:runbooks\ntitle:Restart\ncontext:text\nsource:/runbooks/restart.md\n"
+    );
+
+    let chunk = QAChunk::create("/docs/copy.md", "text");
+    assert_eq!(
+        QALLM::format_context(&chunk, ""),
+        "context:text\nsource:/docs/copy.md\n"
+    );
+}
@@ -0,0 +1,115 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs;
+use std::path::Path;
+
+use askbend::Config;
+use askbend::QAGitSource;
+use git2::IndexAddOption;
+use git2::Repository;
+use git2::RepositoryInitOptions;
+use git2::Signature;
+
+fn commit_all(repo: &Repository, message: &str) {
+    let mut index = repo.index().unwrap();
+    index
+        .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
+        .unwrap();
+    index.update_all(["*"].iter(), None).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = Signature::now("askbend", "askbend@example.com").unwrap();
+    let parents = match repo.head() {
+        Ok(head) => vec![head.peel_to_commit().unwrap()],
+        Err(_) => vec![],
+    };
+    let parents = parents.iter().collect::<Vec<_>>();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &parents,
+    )
+    .unwrap();
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_qa_git_source() {
+    let upstream_dir = tempfile::tempdir().unwrap();
+    let checkout_dir = tempfile::tempdir().unwrap();
+    let upstream = upstream_dir.path();
+
+    let mut opts = RepositoryInitOptions::new();
+    opts.initial_head("main");
+    let repo = Repository::init_opts(upstream, &opts).unwrap();
+    write(upstream, "docs/a.md", "# A");
+    write(upstream, "docs/b.md", "# B");
+    write(upstream, "README.md", "# Readme");
+    commit_all(&repo, "init");
+
+    let mut conf = Config::default();
+    conf.qa.git_url = format!("file://{}", upstream.display());
+    conf.qa.git_subdir = "docs".to_string();
+    conf.qa.git_checkout_dir = checkout_dir.path().join("repo").display().to_string();
+    let source = QAGitSource::create(&conf.qa);
+
+    // Clone.
+    let first_sha = source.sync().unwrap();
+    let root = source.root().unwrap();
+    assert!(root.ends_with("docs"));
+    assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "# A");
+
+    // Fetch the new commit, only the changes under the subdir are reported.
+    write(upstream, "docs/a.md", "# A2");
+    write(upstream, "docs/c.md", "# C");
+    write(upstream, "README.md", "# Readme2");
+    fs::remove_file(upstream.join("docs/b.md")).unwrap();
+    commit_all(&repo, "update");
+
+    let second_sha = source.sync().unwrap();
+    assert_ne!(first_sha, second_sha);
+    assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "# A2");
+    assert!(!root.join("b.md").exists());
+
+    let mut changed = source
+        .changed_files(&first_sha, &second_sha)
+        .unwrap()
+        .unwrap();
+    changed.sort();
+    assert_eq!(changed, vec![
+        root.join("a.md"),
+        root.join("b.md"),
+        root.join("c.md")
+    ]);
+
+    // Nothing changed.
+    assert_eq!(source.sync().unwrap(), second_sha);
+    let changed = source.changed_files(&second_sha, &second_sha).unwrap();
+    assert_eq!(changed, Some(vec![]));
+
+    // Unknown commit.
+    let changed = source
+        .changed_files("0000000000000000000000000000000000000000", &second_sha)
+        .unwrap();
+    assert!(changed.is_none());
+}
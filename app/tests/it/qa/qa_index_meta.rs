@@ -0,0 +1,69 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::QAChunk;
+use askbend::QADuplicateGroup;
+use askbend::QAIndexMeta;
+
+fn paths(paths: &[&str]) -> Vec<String> {
+    paths.iter().map(|x| x.to_string()).collect()
+}
+
+#[test]
+fn test_qa_index_meta_same_chunking() {
+    let mut conf = Config::default();
+    let meta = QAIndexMeta::create(&conf.qa, "data/");
+    assert!(meta.same_chunking(&QAIndexMeta::create(&conf.qa, "data/")));
+
+    conf.qa.dedup = "exact".to_string();
+    assert!(!meta.same_chunking(&QAIndexMeta::create(&conf.qa, "data/")));
+
+    let mut conf = Config::default();
+    conf.qa.dedup_prefer_paths = vec!["docs/latest/**".to_string()];
+    assert!(!meta.same_chunking(&QAIndexMeta::create(&conf.qa, "data/")));
+}
+
+#[test]
+fn test_qa_index_meta_merges() {
+    let mut meta = QAIndexMeta {
+        dedup_merges: vec![
+            paths(&["/a.md", "/b.md"]),
+            paths(&["/b.md", "/c.md"]),
+            paths(&["/d.md", "/e.md"]),
+        ],
+        ..Default::default()
+    };
+    // The merged documents are re-embedded with the changed one, transitively.
+    assert_eq!(
+        meta.merged_paths(&paths(&["/a.md"])),
+        paths(&["/a.md", "/b.md", "/c.md"])
+    );
+    assert_eq!(meta.merged_paths(&paths(&["/f.md"])), paths(&["/f.md"]));
+
+    // The merges of the re-embedded documents are replaced by the new ones.
+    let previous = meta.dedup_merges.clone();
+    let groups = vec![QADuplicateGroup {
+        kept: QAChunk::create("/c.md", "license"),
+        removed: vec![
+            QAChunk::create("/c.md", "license"),
+            QAChunk::create("/a.md", "license"),
+        ],
+    }];
+    meta.record_merges(&previous, &paths(&["/a.md", "/b.md", "/c.md"]), &groups);
+    assert_eq!(meta.dedup_merges, vec![
+        paths(&["/d.md", "/e.md"]),
+        paths(&["/c.md", "/a.md"])
+    ]);
+}
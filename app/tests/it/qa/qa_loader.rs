@@ -0,0 +1,137 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use askbend::Config;
+use askbend::QALoader;
+use askbend::QALoaderConfig;
+use askbend::QALoaderKind;
+
+fn loader_conf(glob: &str, loader: &str) -> QALoaderConfig {
+    QALoaderConfig {
+        glob: glob.to_string(),
+        loader: loader.to_string(),
+    }
+}
+
+#[test]
+fn test_qa_loader_kind() {
+    let mut conf = Config::default();
+    conf.qa.loaders = vec![
+        loader_conf("**/*.md", "markdown"),
+        loader_conf("**/*.mdx", "mdx"),
+        loader_conf("**/sidebar.json", "text"),
+        loader_conf("**/*.json", "json"),
+        loader_conf("**/*.rs", "code"),
+    ];
+    conf.qa.ignored_paths = vec!["**/ignore_dir/**".to_string()];
+    let loader = QALoader::create(&conf.qa).unwrap();
+
+    assert_eq!(loader.kind("/data/a.md"), Some(QALoaderKind::Markdown));
+    assert_eq!(loader.kind("/data/a.mdx"), Some(QALoaderKind::Mdx));
+    // The first matched loader is used.
+    assert_eq!(loader.kind("/data/sidebar.json"), Some(QALoaderKind::Text));
+    assert_eq!(loader.kind("/data/meta.json"), Some(QALoaderKind::Json));
+    assert_eq!(loader.kind("/data/lib.rs"), Some(QALoaderKind::Code));
+    assert_eq!(loader.kind("/data/ignore_dir/a.md"), None);
+    assert_eq!(loader.kind("/data/a.png"), None);
+
+    conf.qa.loaders = vec![loader_conf("**/*.md", "pdf")];
+    assert!(QALoader::create(&conf.qa).is_err());
+    assert!(QALoaderKind::from_str("Markdown").is_ok());
+}
+
+#[tokio::test]
+async fn test_qa_loader_load_directory() {
+    let mut conf = Config::default();
+    conf.qa.loaders = vec![
+        loader_conf("**/*.md", "markdown"),
+        loader_conf("**/*.rs", "code"),
+    ];
+    conf.qa.ignored_paths = vec!["**/ignore_dir/**".to_string()];
+    conf.qa.chunk_size = 300;
+    let loader = QALoader::create(&conf.qa).unwrap();
+
+    let root = std::fs::canonicalize("tests/testdata").unwrap();
+    let documents = loader
+        .load_directory(&format!("{}/", root.display()))
+        .await
+        .unwrap();
+    let mut paths = documents
+        .iter()
+        .map(|x| {
+            x.path
+                .strip_prefix(&root.display().to_string())
+                .unwrap()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert_eq!(paths, vec!["/1.md", "/2.md", "/hash.md", "/rust.rs"]);
+
+    let chunks = loader.split_documents(&documents).unwrap();
+    assert!(chunks.len() > documents.len());
+    // The code is split at the definitions.
+    let code_chunks = chunks
+        .iter()
+        .filter(|x| x.path.ends_with("rust.rs"))
+        .collect::<Vec<_>>();
+    assert!(code_chunks.len() > 1);
+    assert!(code_chunks.iter().all(|x| x.content.len() <= 300));
+    assert!(
+        code_chunks
+            .iter()
+            .any(|x| x.content.starts_with("fn extract_code_snippets"))
+    );
+    // The chunks are numbered in each document.
+    let indexes = code_chunks.iter().map(|x| x.index).collect::<Vec<_>>();
+    assert_eq!(indexes, (0..code_chunks.len()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_qa_loader_transform() {
+    let mdx = r#"import Tabs from '@theme/Tabs';
+import TabItem from '@theme/TabItem';
+
+# Install
+
+{/* a comment */}
+<Tabs groupId="os">
+<TabItem value="linux" label="Linux">
+
+Run the <Link to="/install">installer</Link>:
+
+```html
+<Tabs>keep in code</Tabs>
+```
+
+</TabItem>
+<DetailsWrap
+  title="More"
+>
+text
+</DetailsWrap>
+</Tabs>"#;
+    let expect =
+        "\n# Install\n\n\nRun the installer:\n\n```html\n<Tabs>keep in code</Tabs>\n```\n\ntext";
+    assert_eq!(QALoaderKind::Mdx.transform(mdx), expect);
+
+    let json = r#"{"label": "Load Data", "position": 2, "link": {"type": "generated-index", "slug": "/load"}, "tags": ["a", "b"], "x": null}"#;
+    let expect = "label: Load Data\nlink.slug: /load\nlink.type: generated-index\nposition: 2\ntags[0]: a\ntags[1]: b";
+    assert_eq!(QALoaderKind::Json.transform(json), expect);
+    assert_eq!(QALoaderKind::Json.transform("not json"), "not json");
+
+    assert_eq!(QALoaderKind::Text.transform("text"), "text");
+}
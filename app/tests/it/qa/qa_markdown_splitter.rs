@@ -0,0 +1,282 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::QAChunk;
+use askbend::QAEmbedding;
+use askbend::QAFrontMatter;
+use askbend::QALoader;
+use askbend::QAMarkdownSplitter;
+
+#[test]
+fn test_qa_front_matter() {
+    let page = "---\ntitle: \"COPY INTO\"\nsidebar_label: Copy\nslug: /sql/copy-into\ntags:\n  - load\n---\n# Syntax\n";
+    let (front_matter, body) = QAFrontMatter::parse(page);
+    assert_eq!(front_matter.title, "COPY INTO");
+    assert_eq!(front_matter.sidebar_label, "Copy");
+    assert_eq!(front_matter.slug, "/sql/copy-into");
+    assert_eq!(front_matter.tags, vec!["load".to_string()]);
+    assert_eq!(body, "# Syntax\n");
+
+    let (front_matter, _) =
+        QAFrontMatter::parse("---\nversion: \"1.2\"\ntags: [sql, 'load']\n---\n");
+    assert_eq!(front_matter.version, "1.2");
+    assert_eq!(front_matter.tags, vec![
+        "sql".to_string(),
+        "load".to_string()
+    ]);
+
+    let (front_matter, _) = QAFrontMatter::parse("---\nsidebar_label: 'Copy'\n---\n");
+    assert_eq!(front_matter.page_title(), "Copy");
+
+    // Not a front matter.
+    let page = "# Title\n---\ntext\n";
+    let (front_matter, body) = QAFrontMatter::parse(page);
+    assert_eq!(front_matter, QAFrontMatter::default());
+    assert_eq!(body, page);
+}
+
+#[test]
+fn test_qa_markdown_splitter() {
+    let page = r#"---
+title: Loading Data
+slug: /load-data
+---
+Intro of loading.
+
+## COPY INTO
+
+Load from a stage.
+
+```sql
+# not a heading
+COPY INTO t FROM @stage;
+```
+
+### Options
+
+The copy options.
+
+## Streaming
+
+Load from kafka.
+"#;
+    let chunks = QAMarkdownSplitter::create().split("/load.md", page);
+    let sections = chunks
+        .iter()
+        .map(|x| (x.headings.as_str(), x.content.lines().next().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(sections, vec![
+        ("", "Intro of loading."),
+        ("COPY INTO", "## COPY INTO"),
+        ("COPY INTO > Options", "### Options"),
+        ("Streaming", "## Streaming"),
+    ]);
+    assert!(chunks[1].content.contains("# not a heading"));
+    assert!(chunks.iter().all(|x| x.title == "Loading Data"));
+    assert!(chunks.iter().all(|x| x.slug == "/load-data"));
+    assert!(chunks.iter().all(|x| x.path == "/load.md"));
+
+    // The title falls back to the first heading.
+    let chunks = QAMarkdownSplitter::create().split("/a.md", "# Overview\n\ntext\n");
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].title, "Overview");
+    assert_eq!(chunks[0].headings, "Overview");
+
+    // Long sections are split by lines.
+    let text = (0..20)
+        .map(|x| format!("line {}", x))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let chunks = QAMarkdownSplitter::create()
+        .with_chunk_size(30)
+        .split("/a.md", &text);
+    assert!(chunks.len() > 1);
+    assert!(chunks.iter().all(|x| x.content.len() < 30));
+}
+
+#[test]
+fn test_qa_chunk_source() {
+    let mut chunk = QAChunk::create("/data/load.md", "text");
+    assert_eq!(chunk.source("https://docs.databend.com/"), "/data/load.md");
+
+    chunk.slug = "/load-data".to_string();
+    assert_eq!(
+        chunk.source("https://docs.databend.com/"),
+        "https://docs.databend.com/load-data"
+    );
+    assert_eq!(chunk.source(""), "/data/load.md");
+}
+
+#[test]
+fn test_qa_chunk_locate() {
+    // The docs of the default config are under `data/docs/doc`.
+    let mut chunk = QAChunk::create("data/docs/doc/12-load-data/02-load-db/copy.md", "text");
+    chunk.locate("data/", "docs/doc");
+    assert_eq!(chunk.doc_section, "12-load-data");
+    assert_eq!(chunk.doc_path, "12-load-data/02-load-db/copy.md");
+
+    let mut chunk = QAChunk::create("data/docs/doc/00-overview.md", "text");
+    chunk.locate("data", "/docs/doc/");
+    assert_eq!(chunk.doc_section, "");
+    assert_eq!(chunk.doc_path, "00-overview.md");
+
+    // Not under the docs root, relative to the source root.
+    let mut chunk = QAChunk::create("data/docs/dev/20-rfcs/rfc.md", "text");
+    chunk.locate("data/", "docs/doc");
+    assert_eq!(chunk.doc_section, "docs");
+    assert_eq!(chunk.doc_path, "docs/dev/20-rfcs/rfc.md");
+
+    // Without docs root, the first directory of the source root is the section.
+    let mut chunk = QAChunk::create("data/docs/doc/12-load-data/01-s3.md", "text");
+    chunk.locate("data/", "");
+    assert_eq!(chunk.doc_section, "docs");
+    assert_eq!(chunk.doc_path, "docs/doc/12-load-data/01-s3.md");
+
+    // Not under the source root.
+    let mut chunk = QAChunk::create("/data/docs-old/intro.md", "text");
+    chunk.locate("/data/docs", "");
+    assert_eq!(chunk.doc_section, "data");
+    assert_eq!(chunk.doc_path, "data/docs-old/intro.md");
+}
+
+#[tokio::test]
+async fn test_qa_embedding_add_metadata() {
+    // The docs of the repo, as loaded by the default config.
+    let root = std::fs::canonicalize("../data").unwrap();
+    let mut conf = Config::default();
+    conf.qa.path = format!("{}/", root.display());
+    conf.qa.docs_root = "docs/doc".to_string();
+    conf.qa.docs_version = "1.2".to_string();
+
+    let loader = QALoader::create(&conf.qa).unwrap();
+    let documents = loader
+        .load_directory(&format!("{}docs/doc/12-load-data/", conf.qa.path))
+        .await
+        .unwrap();
+    let mut chunks = loader.split_documents(&documents).unwrap();
+    assert!(!chunks.is_empty());
+    QAEmbedding::add_metadata(&conf.qa, &conf.qa.path, &mut chunks);
+
+    for chunk in &chunks {
+        assert_eq!(chunk.doc_section, "12-load-data", "{}", chunk.path);
+        assert!(chunk.doc_path.starts_with("12-load-data/"));
+        assert_eq!(chunk.version, "1.2");
+    }
+}
+
+#[test]
+fn test_qa_markdown_splitter_code_blocks() {
+    let page = "## Example\n\nCreate the table:\n\n```sql\nCREATE TABLE t (\n  a INT,\n  b VARCHAR\n);\n```\n\nDone.\n";
+
+    // The code block is kept whole even if it's larger than the chunk size.
+    let chunks = QAMarkdownSplitter::create()
+        .with_chunk_size(20)
+        .split("/a.md", page);
+    assert!(
+        chunks
+            .iter()
+            .any(|x| x.content == "```sql\nCREATE TABLE t (\n  a INT,\n  b VARCHAR\n);\n```")
+    );
+
+    let chunks = QAMarkdownSplitter::create()
+        .with_chunk_size(20)
+        .with_split_code_blocks(true)
+        .split("/a.md", page);
+    assert!(chunks.iter().all(|x| x.content.len() < 20));
+}
+
+#[test]
+fn test_qa_markdown_splitter_overlap() {
+    let text = "aaaa\nbbbb\ncccc\ndddd\neeee\n";
+    let contents = |splitter: QAMarkdownSplitter| {
+        splitter
+            .with_chunk_size(12)
+            .split("/a.md", text)
+            .into_iter()
+            .map(|x| x.content)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(contents(QAMarkdownSplitter::create()), vec![
+        "aaaa\nbbbb",
+        "cccc\ndddd",
+        "eeee"
+    ]);
+    assert_eq!(
+        contents(QAMarkdownSplitter::create().with_chunk_overlap(5)),
+        vec!["aaaa\nbbbb", "bbbb\ncccc", "cccc\ndddd", "dddd\neeee"]
+    );
+}
+
+#[test]
+fn test_qa_markdown_splitter_section_id() {
+    let page = "Intro.\n\n## A\n\naaaa\nbbbb\ncccc\n\n## B\n\ndddd\n";
+    let chunks = QAMarkdownSplitter::create()
+        .with_chunk_size(12)
+        .split("/a.md", page);
+    let sections = chunks
+        .iter()
+        .map(|x| (x.headings.as_str(), x.section_id))
+        .collect::<Vec<_>>();
+    // The chunks of a long section share its id.
+    assert_eq!(sections, vec![("", 0), ("A", 1), ("A", 1), ("B", 2)]);
+}
+
+#[test]
+fn test_qa_markdown_splitter_min_chunk_size() {
+    let page = "# Title\n\n## A\n\nSee B.\n\n## B\n\nThe details of B.\n";
+
+    let chunks = QAMarkdownSplitter::create().split("/a.md", page);
+    assert_eq!(chunks.len(), 3);
+
+    let chunks = QAMarkdownSplitter::create()
+        .with_min_chunk_size(20)
+        .split("/a.md", page);
+    // The title section is merged into the next section.
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0].content, "# Title\n\n## A\n\nSee B.");
+    assert_eq!(chunks[0].headings, "Title > A");
+
+    let chunks = QAMarkdownSplitter::create()
+        .with_min_chunk_size(30)
+        .split("/a.md", page);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].headings, "Title > B");
+
+    // The last small chunk is merged into the previous one.
+    let page = "## A\n\nThe details of A.\n\n## B\n\nSee A.\n";
+    let chunks = QAMarkdownSplitter::create()
+        .with_min_chunk_size(20)
+        .split("/a.md", page);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(
+        chunks[0].content,
+        "## A\n\nThe details of A.\n\n## B\n\nSee A."
+    );
+    assert_eq!(chunks[0].headings, "A");
+}
+
+#[test]
+fn test_qa_markdown_splitter_prepend_headings() {
+    let page = "---\ntitle: Loading Data\n---\n## COPY INTO\n\nLoad from a stage.\n";
+    let chunks = QAMarkdownSplitter::create()
+        .with_prepend_headings(true)
+        .split("/a.md", page);
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(
+        chunks[0].content,
+        "Loading Data > COPY INTO\n\n## COPY INTO\n\nLoad from a stage."
+    );
+}
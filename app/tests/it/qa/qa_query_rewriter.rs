@@ -0,0 +1,70 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QAQueryRewriter;
+
+use crate::qa::chunk;
+use crate::qa::fake_llm::FakeLLM;
+
+#[test]
+fn test_qa_query_rewriter_parse_queries() {
+    let rewriter = QAQueryRewriter::create(FakeLLM::create("")).with_max_queries(2);
+    let generation = "- How to load the csv files from a stage\n\
+                      2. COPY INTO a table from the files\n\
+                      - how to load the CSV files from a stage\n\
+                      - Create a stage\n\
+                      keywords: COPY INTO, STAGE, FILE_FORMAT\n";
+    let queries = rewriter.parse_queries("how to import csv?", generation);
+    assert_eq!(queries, vec![
+        "how to import csv?",
+        "How to load the csv files from a stage",
+        "COPY INTO a table from the files",
+        "COPY INTO STAGE FILE_FORMAT",
+    ]);
+}
+
+#[tokio::test]
+async fn test_qa_query_rewriter_rewrite() {
+    let llm = FakeLLM::create("- Drop a table\nkeywords:");
+    let rewriter = QAQueryRewriter::create(llm.clone());
+    let queries = rewriter.rewrite("how to delete a table?").await;
+    assert_eq!(queries, vec!["how to delete a table?", "Drop a table"]);
+
+    let prompts = llm.prompts();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("Question: how to delete a table?"));
+}
+
+#[test]
+fn test_qa_query_rewriter_merge() {
+    let first = vec![
+        chunk("default", "/docs/copy.md", 0.8),
+        chunk("default", "/docs/stage.md", 0.5),
+    ];
+    let second = vec![
+        chunk("default", "/docs/stage.md", 0.7),
+        chunk("default", "/docs/csv.md", 0.5),
+    ];
+    let merged = QAQueryRewriter::merge(vec![first, second], 3);
+    let merged = merged
+        .iter()
+        .map(|x| (x.path.as_str(), x.score))
+        .collect::<Vec<_>>();
+    assert_eq!(merged, vec![
+        ("/docs/copy.md", 0.8),
+        // The chunk retrieved by both queries keeps its best score.
+        ("/docs/stage.md", 0.7),
+        ("/docs/csv.md", 0.5),
+    ]);
+}
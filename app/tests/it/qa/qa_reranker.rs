@@ -0,0 +1,127 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use askbend::QAChunk;
+use askbend::QALLMReranker;
+use askbend::QALexicalReranker;
+use askbend::QAReranker;
+use askbend::QARerankerKind;
+
+use crate::qa::fake_llm::FakeLLM;
+
+fn candidates() -> Vec<QAChunk> {
+    vec![
+        QAChunk {
+            similarity: 0.9,
+            ..QAChunk::create("/stage.md", "CREATE STAGE creates an internal stage.")
+        },
+        QAChunk {
+            similarity: 0.8,
+            ..QAChunk::create("/overview.md", "Databend is a cloud data warehouse.")
+        },
+        QAChunk {
+            similarity: 0.7,
+            headings: "COPY INTO".to_string(),
+            ..QAChunk::create("/copy.md", "Load data files from a stage into a table.")
+        },
+    ]
+}
+
+fn paths(chunks: &[QAChunk]) -> Vec<&str> {
+    chunks.iter().map(|x| x.path.as_str()).collect()
+}
+
+#[test]
+fn test_qa_reranker_kind() {
+    assert_eq!(QARerankerKind::from_str("").unwrap(), QARerankerKind::Off);
+    assert_eq!(
+        QARerankerKind::from_str("Lexical").unwrap(),
+        QARerankerKind::Lexical
+    );
+    assert_eq!(
+        QARerankerKind::from_str("llm").unwrap(),
+        QARerankerKind::LLM
+    );
+    assert!(QARerankerKind::from_str("cohere").is_err());
+}
+
+#[tokio::test]
+async fn test_qa_lexical_reranker() {
+    let reranker = QALexicalReranker::create();
+    let question = "How to load data with COPY INTO from a stage?";
+
+    let scores = reranker.scores(question, &candidates()).await.unwrap();
+    // Terms: load, data, copy, into, stage.
+    assert_eq!(scores, vec![0.2, 0.2, 1.0]);
+
+    let chunks = reranker.rerank(question, candidates(), 2).await.unwrap();
+    assert_eq!(paths(&chunks), vec!["/copy.md", "/stage.md"]);
+    assert_eq!(chunks[0].similarity, 0.7);
+    assert_eq!(chunks[0].rerank_score, 1.0);
+}
+
+#[tokio::test]
+async fn test_qa_llm_reranker() {
+    let llm = FakeLLM::create("[1]: 3\n[2]: 0\n[3]: 9\n");
+    let reranker = QALLMReranker::create(llm.clone());
+
+    let chunks = reranker
+        .rerank("How to load data?", candidates(), 2)
+        .await
+        .unwrap();
+    assert_eq!(paths(&chunks), vec!["/copy.md", "/stage.md"]);
+    assert_eq!(chunks[0].rerank_score, 0.9);
+
+    let prompts = llm.prompts();
+    assert_eq!(prompts.len(), 1);
+    assert!(prompts[0].contains("Question: How to load data?"));
+    assert!(prompts[0].contains("[3] COPY INTO\nLoad data files from a stage into a table."));
+}
+
+struct FailingReranker {}
+
+#[async_trait::async_trait]
+impl QAReranker for FailingReranker {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    async fn scores(&self, _question: &str, _chunks: &[QAChunk]) -> Result<Vec<f32>> {
+        Err(anyhow!("rerank service unavailable"))
+    }
+}
+
+#[tokio::test]
+async fn test_qa_reranker_fallback() {
+    // The failed rerank keeps the top ones of the vector search.
+    let chunks = FailingReranker {}
+        .rerank("How to load data?", candidates(), 2)
+        .await
+        .unwrap();
+    assert_eq!(paths(&chunks), vec!["/stage.md", "/overview.md"]);
+    assert_eq!(chunks[0].rerank_score, 0.0);
+}
+
+#[test]
+fn test_qa_llm_reranker_parse_scores() {
+    assert_eq!(
+        QALLMReranker::parse_scores("[2]: 7\n1: 10\n 3 - 4.5\n[9]: 8\nnone", 3),
+        vec![1.0, 0.7, 0.45]
+    );
+    assert_eq!(QALLMReranker::parse_scores("", 2), vec![0.0, 0.0]);
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::Config;
+use askbend::QAChunk;
+use askbend::QASearch;
+use askbend::QASearchFilter;
+use askbend::QASearchHit;
+
+#[test]
+fn test_qa_search_filter_conditions() {
+    assert_eq!(QASearchFilter::default().conditions(), "");
+
+    let filter: QASearchFilter =
+        serde_json::from_str(r#"{"path": "12-load_data/", "heading": "User's COPY"}"#).unwrap();
+    assert_eq!(
+        filter.conditions(),
+        " AND path LIKE '%12-load\\\\_data/%' AND lower(headings) LIKE '%user''s copy%'"
+    );
+
+    let filter: QASearchFilter = serde_json::from_str(
+        r#"{"path_prefix": "/sql/", "sections": ["sql"], "exclude_sections": ["dev"], "version": "1.2", "tags": ["load"]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        filter.conditions(),
+        " AND doc_path LIKE 'sql/%' AND doc_section IN ('sql') AND (doc_section IS NULL OR doc_section NOT IN ('dev')) AND version = '1.2' AND concat(',', tags, ',') LIKE '%,load,%'"
+    );
+
+    // A typo in the filter is an error instead of no filter.
+    assert!(serde_json::from_str::<QASearchFilter>(r#"{"paths": "a/"}"#).is_err());
+}
+
+#[test]
+fn test_qa_search_hit() {
+    let chunk = QAChunk {
+        title: "COPY INTO".to_string(),
+        slug: "/load/copy".to_string(),
+        headings: "Examples".to_string(),
+        similarity: 0.8,
+        score: 0.6,
+        collection: "default".to_string(),
+        ..QAChunk::create("/data/docs/copy.md", "COPY INTO t FROM @s")
+    };
+    let hit = QASearchHit::create(chunk.clone(), "https://docs.databend.com");
+    assert_eq!(hit.source, "https://docs.databend.com/load/copy");
+    assert_eq!(hit.path, "/data/docs/copy.md");
+    assert_eq!(hit.collection, "default");
+    assert_eq!(hit.score, 0.6);
+
+    let hit = QASearchHit::create(chunk, "");
+    assert_eq!(hit.source, "/data/docs/copy.md");
+}
+
+#[tokio::test]
+async fn test_qa_search_page_overflow() {
+    let search = QASearch::create(&Config::default());
+    let err = search
+        .search("copy", None, usize::MAX, 10)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too large"));
+}
@@ -0,0 +1,55 @@
+// Copyright 2023 Databend Labs
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use askbend::QATextSplitter;
+use llmchain::Document;
+use llmchain::DocumentSplitter;
+use llmchain::Documents;
+
+fn split(splitter: &QATextSplitter, content: &str) -> Vec<String> {
+    let documents = Documents::from(vec![Document::create("a.txt", content)]);
+    splitter
+        .split_documents(&documents)
+        .unwrap()
+        .iter()
+        .map(|x| x.content)
+        .collect()
+}
+
+#[test]
+fn test_qa_text_splitter() {
+    let text = "line one\nline two\nline three\n\nline four";
+    let splitter = QATextSplitter::create().with_chunk_size(15);
+    assert_eq!(split(&splitter, text), vec![
+        "line one",
+        "line two",
+        "line three\n",
+        "line four"
+    ]);
+
+    // The small lines are merged.
+    let splitter = QATextSplitter::create();
+    assert_eq!(split(&splitter, text), vec![text.to_string()]);
+
+    // The trailing lines within the overlap are repeated in the next chunk.
+    let text = "line one\nline two\nline three\nline four";
+    let splitter = QATextSplitter::create()
+        .with_chunk_size(20)
+        .with_chunk_overlap(10);
+    assert_eq!(split(&splitter, text), vec![
+        "line one\nline two",
+        "line two\nline three",
+        "line four"
+    ]);
+}